@@ -0,0 +1,65 @@
+use {
+    super::{
+        EngineError, GoOptions,
+        options::{HASH, Options},
+        search::{History, Search, Signals},
+        tt::TranspositionTable,
+    },
+    chess::Board,
+    std::{
+        str::FromStr,
+        time::{Duration, Instant},
+    },
+};
+
+// Deep enough that the pruning and move ordering matter, without taking long to run
+pub const DEFAULT_BENCH_DEPTH: usize = 10;
+
+// A fixed mix of openings, middlegames and endgames, so node counts can be compared between versions
+const BENCH_POSITIONS: [&str; 14] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    "4r1k1/r1q2ppp/ppp2n2/4P3/5Rb1/1N1BQ3/PPP3PP/R5K1 w - - 1 17",
+    "2rqkb1r/ppp2p2/2npb1p1/1N1Nn2p/2P1PP2/8/PP2B1PP/R1BQK2R b KQ - 0 11",
+    "r1bq1r1k/b1p1npp1/p2p3p/1p6/3PP3/1B2NN2/PP3PPP/R2Q1RK1 w - - 1 16",
+    "3r1rk1/p5pp/bpp1pp2/8/q1PP1P2/b3P3/P2NQRPP/1R2B1K1 b - - 6 22",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "3b4/5kp1/1p1p1p1p/pP1PpP1P/P1P1P3/3KN3/8/8 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+];
+
+// Searches every bench position to the same depth from an empty table, returning the nodes and time it took.
+// Nothing is left over between the positions, so the node count only changes when the search itself does
+pub fn bench(depth: usize, options: &Options) -> Result<(usize, Duration), EngineError> {
+    let start = Instant::now();
+    let mut nodes = 0;
+
+    let go_options = GoOptions {
+        depth,
+        ..GoOptions::default()
+    };
+
+    for fen in BENCH_POSITIONS {
+        let board = Board::from_str(fen)?;
+        let mut transposition_table = TranspositionTable::new(options.spin(HASH) as usize);
+
+        let mut search = Search::new(
+            board,
+            &go_options,
+            options,
+            Signals::default(),
+            History::default(),
+            &mut transposition_table,
+        );
+        search.iterative_deepening()?;
+
+        nodes += search.nodes();
+    }
+
+    Ok((nodes, start.elapsed()))
+}
@@ -0,0 +1,83 @@
+use {
+    super::Score,
+    chess::{
+        BitBoard, Board, Color, EMPTY, Piece, Square, get_bishop_moves, get_knight_moves,
+        get_pawn_attacks, get_rook_moves,
+    },
+};
+
+// What each square a piece can go to is worth, around the squares an average piece of the type has.
+// Minor pieces are worth more per square, a queen has so many that a few more barely matter
+const MOBILITY: [(Piece, Score, isize); 4] = [
+    (Piece::Knight, Score::new(4, 4), 4),
+    (Piece::Bishop, Score::new(5, 5), 6),
+    (Piece::Rook, Score::new(2, 4), 7),
+    (Piece::Queen, Score::new(1, 2), 13),
+];
+
+// How freely the pieces of the given side move. Squares taken by our own pieces don't count,
+// and neither do squares an enemy pawn covers, a piece going there would just get chased away
+pub fn mobility(board: &Board, color: Color) -> Score {
+    let our_pieces = board.color_combined(color);
+    let their_pawns = board.pieces(Piece::Pawn) & board.color_combined(!color);
+
+    let mut pawn_attacks = EMPTY;
+
+    for square in their_pawns {
+        pawn_attacks |= get_pawn_attacks(square, !color, !EMPTY);
+    }
+
+    let area = !(our_pieces | pawn_attacks);
+    let mut score = Score::default();
+
+    for (piece, weight, average) in MOBILITY {
+        for square in board.pieces(piece) & our_pieces {
+            let moves = (attacks(board, piece, square) & area).popcnt() as isize;
+
+            score += weight * (moves - average);
+        }
+    }
+
+    score
+}
+
+// The squares a piece on the square attacks, nothing for pawns and kings
+pub fn attacks(board: &Board, piece: Piece, square: Square) -> BitBoard {
+    let occupied = *board.combined();
+
+    match piece {
+        Piece::Knight => get_knight_moves(square),
+        Piece::Bishop => get_bishop_moves(square, occupied),
+        Piece::Rook => get_rook_moves(square, occupied),
+        Piece::Queen => get_bishop_moves(square, occupied) | get_rook_moves(square, occupied),
+        Piece::Pawn | Piece::King => EMPTY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    fn mobility_of(fen: &str) -> Score {
+        let board = Board::from_str(fen).expect("Valid FEN");
+
+        mobility(&board, Color::White)
+    }
+
+    #[test]
+    fn a_bishop_behind_its_own_pawns_is_worth_less() {
+        let buried = mobility_of("4k3/8/8/8/8/1P1P4/2B5/4K3 w - - 0 1");
+        let free = mobility_of("4k3/8/8/8/8/1P6/2B5/4K3 w - - 0 1");
+
+        assert!(free.middle_game > buried.middle_game);
+    }
+
+    #[test]
+    fn squares_covered_by_enemy_pawns_dont_count() {
+        let open = mobility_of("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+        let covered = mobility_of("4k3/8/2p5/8/8/2N5/8/4K3 w - - 0 1");
+
+        // The pawn on c6 covers b5 and d5, two of the knight's eight squares
+        assert_eq!(open - covered, Score::new(4, 4) * 2);
+    }
+}
@@ -1,28 +1,53 @@
+mod bench;
+mod mobility;
+mod movepick;
+mod options;
+mod pawns;
 mod pst;
+mod search;
+mod see;
+mod time;
+mod tt;
 mod uci;
 
 use {
+    bench::DEFAULT_BENCH_DEPTH,
     chess::{
         ALL_PIECES, Board, CastleRights, ChessMove, Error as ChessError, MoveGen, Piece, Square,
     },
+    mobility::mobility,
+    options::{CLEAR_HASH, HASH, Options},
+    pawns::PawnTable,
     pst::*,
+    search::{History, Search, Signals, nodes_per_second},
     std::{
         error::Error,
         fmt::{Debug, Display},
         io::{BufRead, Error as IoError, Write, stdin, stdout},
+        ops::{Add, AddAssign, Mul, Sub, SubAssign},
         str::{FromStr, SplitWhitespace},
+        sync::{Arc, Mutex, MutexGuard, atomic::Ordering},
+        thread::{self, JoinHandle},
         time::{Duration, Instant},
     },
+    tt::TranspositionTable,
 };
 
 pub use uci::UCI;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Default)]
 pub struct Engine {
     current_board: Option<Board>,
     moves: Vec<ChessMove>,
+    // Everything before the current position, the search needs it for repetitions and the fifty move rule
+    history: History,
     debug: bool,
-    // Potential cache and data for the engine
+    options: Options,
+    // Shared with the search thread, so it can be stopped or taken off ponder while it's running
+    signals: Signals,
+    search_thread: Option<JoinHandle<()>>,
+    // Kept between searches, the search thread holds the lock while it's running
+    transposition_table: Arc<Mutex<TranspositionTable>>,
 }
 
 #[derive(Debug)]
@@ -30,7 +55,11 @@ pub enum EngineError {
     InvalidCommand(String),
     Chess(ChessError),
     Io(IoError),
-    NoMoves,
+    SearchPanicked,
+    UnknownOption(String),
+    InvalidOptionValue(String, Option<String>),
+    // The move as the GUI sent it, and the ply it was supposed to be played at
+    IllegalMove(String, usize),
 }
 
 impl Display for EngineError {
@@ -43,9 +72,18 @@ impl Display for EngineError {
                 error
             ),
             Self::Io(error) => write!(f, "An I/O error has occured: {}", error),
-            Self::NoMoves => write!(
+            Self::SearchPanicked => write!(f, "The search thread panicked"),
+            Self::UnknownOption(name) => write!(f, "There's no option called {}", name),
+            Self::InvalidOptionValue(name, Some(value)) => {
+                write!(f, "{} isn't a valid value for the option {}", value, name)
+            }
+            Self::InvalidOptionValue(name, None) => {
+                write!(f, "The option {} needs a value", name)
+            }
+            Self::IllegalMove(chess_move, ply) => write!(
                 f,
-                "There's no legal moves that can be made, since I'm in checkmate"
+                "{} isn't a legal move in the position at ply {}",
+                chess_move, ply
             ),
         }
     }
@@ -80,6 +118,86 @@ pub struct GoOptions {
     pub move_time: MoveTime,
 }
 
+const GO_SUBCOMMANDS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+// The game phase of the starting position
+const MAX_PHASE: isize = 24;
+
+// An evaluation term split into its middlegame and endgame value, blended by the game phase at the end
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub struct Score {
+    pub middle_game: isize,
+    pub end_game: isize,
+}
+
+impl Score {
+    pub const fn new(middle_game: isize, end_game: isize) -> Self {
+        Self {
+            middle_game,
+            end_game,
+        }
+    }
+
+    pub fn taper(self, phase: isize) -> isize {
+        (self.middle_game * phase + self.end_game * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.middle_game + other.middle_game,
+            self.end_game + other.end_game,
+        )
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(
+            self.middle_game - other.middle_game,
+            self.end_game - other.end_game,
+        )
+    }
+}
+
+impl Mul<isize> for Score {
+    type Output = Self;
+
+    fn mul(self, factor: isize) -> Self {
+        Self::new(self.middle_game * factor, self.end_game * factor)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Default)]
 pub enum MoveTime {
     #[default]
@@ -100,40 +218,24 @@ impl UCI for Engine {
             // TODO: Optimize the position command, so it doesnt rebuild the whole board if it's just a few moves behind
             // TODO: Support every command from the gui:
             //
-            // setoption name  [value ]
-            //
             // register
             //   later
             //   name
             //   code
             //
-            // go
-            //   searchmoves  ....
-            //   ponder
-            //   wtime x
-            //   btime x
-            //   winc x
-            //   binc x
-            //   movestogo x
-            //   depth x
-            //   nodes x
-            //   mate x
-            //   movetime x
-            //   infinite
-            //
-            // stop
-            //
-            // ponderhit
-            //
-
             let result = match parts.next() {
                 Some("uci") => self.uci(),
                 Some("debug") => self.debug(&mut parts),
                 Some("isready") => self.isready(),
+                Some("setoption") => self.setoption(&mut parts),
                 Some("ucinewgame") => self.ucinewgame(),
                 Some("position") => self.position(&mut parts),
                 Some("go") => self.go(&mut parts),
+                Some("stop") => self.stop(),
+                Some("ponderhit") => self.ponderhit(),
                 Some("quit") => break,
+                // Not part of UCI, searches a fixed set of positions to compare versions of the search
+                Some("bench") => self.bench(&mut parts),
                 _ => Ok(()),
             };
 
@@ -143,7 +245,7 @@ impl UCI for Engine {
             }
         }
 
-        Ok(())
+        self.quit()
     }
 
     fn uci(&self) -> Result<(), EngineError> {
@@ -151,6 +253,11 @@ impl UCI for Engine {
 
         writeln!(stdout, "id name ChessEngine")?;
         writeln!(stdout, "id author Lukas Elias Lund Majland")?;
+
+        for option in self.options.iter() {
+            writeln!(stdout, "{}", option)?;
+        }
+
         writeln!(stdout, "uciok")?;
         stdout.flush()?;
 
@@ -180,90 +287,135 @@ impl UCI for Engine {
         Ok(())
     }
 
+    fn setoption(&mut self, arguments: &mut SplitWhitespace) -> Result<(), EngineError> {
+        if !arguments
+            .next()
+            .is_some_and(|word| word.eq_ignore_ascii_case("name"))
+        {
+            return Err(EngineError::InvalidCommand("setoption".to_string()));
+        }
+
+        let words: Vec<&str> = arguments.collect();
+        let (name, value) = split_setoption(&words, |name| self.options.find(name).is_some());
+
+        if name.is_empty() {
+            return Err(EngineError::InvalidCommand("setoption name".to_string()));
+        }
+
+        self.options.set(&name, value.as_deref())?;
+
+        match self.options.find(&name) {
+            // A running search holds the table, so it has to finish first
+            Some(HASH) => {
+                self.stop()?;
+
+                let megabytes = self.options.spin(HASH) as usize;
+                self.transposition_table().resize(megabytes);
+            }
+            Some(CLEAR_HASH) => {
+                self.stop()?;
+                self.transposition_table().clear();
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     fn ucinewgame(&mut self) -> Result<(), EngineError> {
+        self.stop()?;
+
         self.current_board = None;
         self.moves.clear();
+        self.history = History::default();
+        self.transposition_table().clear();
 
         Ok(())
     }
 
     fn position(&mut self, arguments: &mut SplitWhitespace) -> Result<(), EngineError> {
-        let mut board: Board;
-        match arguments.next() {
-            Some("startpos") => board = Board::default(),
-            Some("fen") => {
-                let fen: Vec<&str> = arguments.take(6).collect();
-
-                if fen.len() != 6 {
-                    return Err(EngineError::InvalidCommand("position fen".to_string()));
-                }
-
-                board = Board::from_str(fen.join(" ").as_str())?;
+        // A position that can't be set up mustn't leave the last one behind, or the next go
+        // would play a move for a position the GUI isn't in anymore
+        let (board, moves, history) = match parse_position(arguments) {
+            Ok(position) => position,
+            Err(error) => {
+                self.current_board = None;
+                self.moves.clear();
+                self.history = History::default();
+
+                return Err(error);
             }
-            _ => return Err(EngineError::InvalidCommand("position".to_string())),
+        };
+
+        self.current_board = Some(board);
+        self.moves = moves;
+        self.history = history;
+
+        Ok(())
+    }
+
+    fn go(&mut self, arguments: &mut SplitWhitespace) -> Result<(), EngineError> {
+        // The GUI waits for a best move after every go, even one without a position to search
+        if let Err(error) = self.start_search(arguments) {
+            let mut stdout = stdout().lock();
+            writeln!(stdout, "bestmove 0000")?;
+            stdout.flush()?;
+
+            return Err(error);
         }
 
-        let mut moves: Vec<ChessMove> = Vec::new();
+        Ok(())
+    }
 
-        match arguments.next() {
-            Some("moves") => {
-                while let Some(move_notation) = arguments.next() {
-                    if move_notation.len() < 4 || move_notation.len() > 5 {
-                        return Err(EngineError::InvalidCommand(
-                            "position ... moves".to_string(),
-                        ));
-                    }
+    fn stop(&mut self) -> Result<(), EngineError> {
+        self.signals.stop.store(true, Ordering::Relaxed);
 
-                    let src_square = Square::from_str(&move_notation[0..2])?;
-                    let dest_square = Square::from_str(&move_notation[2..4])?;
-                    let promotion = match move_notation.chars().nth(4) {
-                        Some('q') => Some(Piece::Queen),
-                        Some('n') => Some(Piece::Knight),
-                        Some('r') => Some(Piece::Rook),
-                        Some('b') => Some(Piece::Bishop),
-                        Some(_) => None,
-                        None => None,
-                    };
-
-                    let chess_move = ChessMove::new(src_square, dest_square, promotion);
-                    board = board.make_move_new(chess_move);
-                    moves.push(chess_move);
-                }
-            }
-            _ => (),
+        match self.search_thread.take() {
+            Some(search_thread) => search_thread
+                .join()
+                .map_err(|_| EngineError::SearchPanicked),
+            None => Ok(()),
         }
+    }
 
-        self.current_board = Some(board);
-        self.moves = moves;
+    // The opponent played the move we were pondering on, so the search goes on but now on our own clock
+    fn ponderhit(&self) -> Result<(), EngineError> {
+        self.signals.ponder.store(false, Ordering::Relaxed);
 
         Ok(())
     }
 
-    fn go(&self, arguments: &mut SplitWhitespace) -> Result<(), EngineError> {
-        let mut stdout = stdout();
+    fn quit(&mut self) -> Result<(), EngineError> {
+        self.stop()
+    }
+}
+
+impl Engine {
+    fn transposition_table(&self) -> MutexGuard<'_, TranspositionTable> {
+        self.transposition_table
+            .lock()
+            .expect("The search thread panicked while holding the transposition table")
+    }
+
+    fn start_search(&mut self, arguments: &mut SplitWhitespace) -> Result<(), EngineError> {
+        // The GUI should never start a search while one is running, but don't let two of them race
+        self.stop()?;
+
+        let board = self
+            .current_board
+            .ok_or(EngineError::InvalidCommand("No position given".to_string()))?;
 
         let mut options = GoOptions::default();
+        let mut arguments = arguments.peekable();
 
         while let Some(subcommand) = arguments.next() {
             match subcommand {
                 "searchmoves" => {
-                    while let Some(move_notation) = arguments.next() {
-                        if move_notation.len() < 4 || move_notation.len() > 5 {
-                            return Err(EngineError::InvalidCommand("go searchmoves".to_string()));
-                        }
-
-                        let src_square = Square::from_str(&move_notation[0..2])?;
-                        let dest_square = Square::from_str(&move_notation[2..4])?;
-                        let promotion = match move_notation.chars().nth(4) {
-                            Some('q') => Some(Piece::Queen),
-                            Some('n') => Some(Piece::Knight),
-                            Some('r') => Some(Piece::Rook),
-                            Some('b') => Some(Piece::Bishop),
-                            Some(_) => None,
-                            None => None,
-                        };
-
-                        let chess_move = ChessMove::new(src_square, dest_square, promotion);
+                    // The move list ends at the next subcommand, if the GUI didn't put searchmoves last
+                    while let Some(move_notation) =
+                        arguments.next_if(|word| !GO_SUBCOMMANDS.contains(word))
+                    {
+                        let chess_move = parse_move(&board, move_notation, self.moves.len() + 1)?;
                         options.search_moves.push(chess_move);
                     }
                 }
@@ -347,147 +499,289 @@ impl UCI for Engine {
             }
         }
 
-        let calculated_move = self.search_moves(options)?;
+        self.signals.stop.store(false, Ordering::Relaxed);
+        self.signals.ponder.store(options.ponder, Ordering::Relaxed);
+
+        let signals = self.signals.clone();
+        let history = self.history.clone();
+        let engine_options = self.options.clone();
+        let transposition_table = Arc::clone(&self.transposition_table);
+        let debug = self.debug;
+
+        self.search_thread = Some(thread::spawn(move || {
+            let result = Self::search_moves(
+                board,
+                options,
+                engine_options,
+                signals,
+                history,
+                &transposition_table,
+                debug,
+            );
+
+            // Nothing waits for the result, the next command mustn't fail because this search did
+            if let (Err(error), true) = (result, debug) {
+                let mut stdout = stdout().lock();
+                let _ = writeln!(stdout, "info string {}", error);
+                let _ = stdout.flush();
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn bench(&mut self, arguments: &mut SplitWhitespace) -> Result<(), EngineError> {
+        let depth = match arguments.next() {
+            Some(depth) => depth
+                .parse()
+                .map_err(|_| EngineError::InvalidCommand("bench".to_string()))?,
+            None => DEFAULT_BENCH_DEPTH,
+        };
+
+        // A search still running would print its lines in the middle of the bench
+        self.stop()?;
 
-        // Make a move
+        let (nodes, elapsed) = bench::bench(depth, &self.options)?;
 
-        writeln!(stdout, "bestmove {}", calculated_move)?;
+        let mut stdout = stdout();
+        writeln!(stdout, "Total time (ms) : {}", elapsed.as_millis())?;
+        writeln!(stdout, "Nodes searched  : {}", nodes)?;
+        writeln!(
+            stdout,
+            "Nodes/second    : {}",
+            nodes_per_second(nodes, elapsed)
+        )?;
         stdout.flush()?;
 
         Ok(())
     }
-}
-
-// TODO: check the go_options etc...
 
-impl Engine {
-    fn search_moves(&self, go_options: GoOptions) -> Result<ChessMove, EngineError> {
+    // Runs on the search thread and answers the GUI with the best move once it's done or stopped
+    fn search_moves(
+        board: Board,
+        go_options: GoOptions,
+        options: Options,
+        signals: Signals,
+        history: History,
+        transposition_table: &Mutex<TranspositionTable>,
+        debug: bool,
+    ) -> Result<(), EngineError> {
         let mut stdout = stdout();
         let now = Instant::now();
 
-        let board = &self
-            .current_board
-            .ok_or(EngineError::InvalidCommand("No position given".to_string()))?;
+        let mut transposition_table = transposition_table
+            .lock()
+            .expect("The search thread panicked while holding the transposition table");
 
-        let result = minimax(
+        let mut search = Search::new(
             board,
-            true,
-            4,
-            f32::NEG_INFINITY as isize,
-            f32::INFINITY as isize,
+            &go_options,
+            &options,
+            signals.clone(),
+            history,
+            &mut transposition_table,
         );
+        let principal_variation = search.iterative_deepening()?;
+        let nodes = search.nodes();
+        let ponder_move = ponder_move(&board, &principal_variation, &transposition_table);
+
+        // Infinite and ponder searches must not report their move before the GUI says stop or ponderhit
+        while !signals.stop.load(Ordering::Relaxed)
+            && (go_options.move_time == MoveTime::Infinite
+                || signals.ponder.load(Ordering::Relaxed))
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
 
-        if self.debug {
+        if debug {
             let elapsed = now.elapsed();
             writeln!(
                 stdout,
-                "info string Elapsed time for the search: {:.2?}",
-                elapsed
+                "info string Elapsed time for the search: {:.2?} ({} nodes)",
+                elapsed, nodes
             )?;
             stdout.flush()?;
         }
 
-        match result.1 {
-            Some(chess_move) => return Ok(chess_move),
-            None => return Err(EngineError::NoMoves),
+        // Checkmate or stalemate, the GUI still waits for an answer
+        match (principal_variation.first(), ponder_move) {
+            (None, _) => writeln!(stdout, "bestmove 0000")?,
+            (Some(calculated_move), None) => writeln!(stdout, "bestmove {}", calculated_move)?,
+            (Some(calculated_move), Some(ponder_move)) => writeln!(
+                stdout,
+                "bestmove {} ponder {}",
+                calculated_move, ponder_move
+            )?,
         }
+        stdout.flush()?;
+
+        Ok(())
     }
 }
 
-fn minimax(
+// Splits what comes after setoption name into the name and the value. Both can have spaces in them,
+// so the name ends at the first "value" that leaves a known option before it, in case an option name
+// has the word value in it. Without a known option the first "value" ends it, without any there's no value
+fn split_setoption(words: &[&str], is_option: impl Fn(&str) -> bool) -> (String, Option<String>) {
+    let splits: Vec<(String, Option<String>)> = words
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| word.eq_ignore_ascii_case("value"))
+        .map(|(index, _)| (words[..index].join(" "), Some(words[index + 1..].join(" "))))
+        .collect();
+
+    splits
+        .iter()
+        .find(|(name, _)| is_option(name))
+        .or(splits.first())
+        .cloned()
+        .unwrap_or_else(|| (words.join(" "), None))
+}
+
+// The reply we expect to our best move, the second move of the principal variation.
+// An iteration cut short can leave a principal variation of one move, then the hash move is the best guess
+fn ponder_move(
     board: &Board,
-    maximizing: bool,
-    depth: usize,
-    mut alpha: isize,
-    mut beta: isize,
-) -> (isize, Option<ChessMove>) {
-    if depth == 0 {
-        return (evaluate(board, maximizing), None);
-    }
+    principal_variation: &[ChessMove],
+    transposition_table: &TranspositionTable,
+) -> Option<ChessMove> {
+    let best_move = *principal_variation.first()?;
+
+    principal_variation.get(1).copied().or_else(|| {
+        let new_board = board.make_move_new(best_move);
+
+        transposition_table
+            .probe(new_board.get_hash())
+            .and_then(|entry| entry.best_move)
+            .filter(|&chess_move| new_board.legal(chess_move))
+    })
+}
 
-    let move_gen = MoveGen::new_legal(board);
+// Sets up the board of a position command, with the moves played to get there and the history they leave
+fn parse_position(
+    arguments: &mut SplitWhitespace,
+) -> Result<(Board, Vec<ChessMove>, History), EngineError> {
+    let mut board: Board;
+    let mut history: History;
+    match arguments.next() {
+        Some("startpos") => {
+            board = Board::default();
+            history = History::default();
+        }
+        Some("fen") => {
+            let fen: Vec<&str> = arguments.take(6).collect();
 
-    if maximizing {
-        let mut max_eval = f32::NEG_INFINITY as isize;
-        let mut move_result = None;
+            if fen.len() != 6 {
+                return Err(EngineError::InvalidCommand("position fen".to_string()));
+            }
 
-        for chess_move in move_gen {
-            let new_board = board.make_move_new(chess_move);
+            board = Board::from_str(fen.join(" ").as_str())?;
 
-            let eval = minimax(&new_board, false, depth - 1, alpha, beta).0;
+            // The chess crate doesn't keep track of the halfmove clock, so it's read from the FEN here
+            let halfmove_clock = fen[4]
+                .parse()
+                .map_err(|_| EngineError::InvalidCommand("position fen".to_string()))?;
+            history = History::new(halfmove_clock);
+        }
+        _ => return Err(EngineError::InvalidCommand("position".to_string())),
+    }
 
-            if max_eval < eval {
-                max_eval = eval;
-                move_result = Some(chess_move);
-            }
+    let mut moves: Vec<ChessMove> = Vec::new();
 
-            alpha = alpha.max(eval);
+    if let Some("moves") = arguments.next() {
+        for move_notation in arguments.by_ref() {
+            let chess_move = parse_move(&board, move_notation, moves.len() + 1)?;
 
-            if beta <= alpha {
-                break;
-            }
+            history.push(&board, chess_move);
+            board = board.make_move_new(chess_move);
+            moves.push(chess_move);
         }
+    }
 
-        return (max_eval, move_result);
-    } else {
-        let mut min_eval = f32::INFINITY as isize;
-        let mut move_result = None;
-
-        for chess_move in move_gen {
-            let new_board = board.make_move_new(chess_move);
+    Ok((board, moves, history))
+}
 
-            let eval = minimax(&new_board, true, depth - 1, alpha, beta).0;
+// Reads a move in the UCI long algebraic notation, like e2e4 or e7e8q, and makes sure it can be played
+fn parse_move(board: &Board, notation: &str, ply: usize) -> Result<ChessMove, EngineError> {
+    let illegal = || EngineError::IllegalMove(notation.to_string(), ply);
 
-            if min_eval > eval {
-                min_eval = eval;
-                move_result = Some(chess_move);
-            }
+    if !notation.is_ascii() || notation.len() < 4 || notation.len() > 5 {
+        return Err(illegal());
+    }
 
-            beta = beta.min(eval);
+    let src_square = Square::from_str(&notation[0..2]).map_err(|_| illegal())?;
+    let dest_square = Square::from_str(&notation[2..4]).map_err(|_| illegal())?;
+    let promotion = match notation.chars().nth(4) {
+        Some('q') => Some(Piece::Queen),
+        Some('n') => Some(Piece::Knight),
+        Some('r') => Some(Piece::Rook),
+        Some('b') => Some(Piece::Bishop),
+        Some(_) => return Err(illegal()),
+        None => None,
+    };
 
-            if beta <= alpha {
-                break;
-            }
-        }
+    let chess_move = ChessMove::new(src_square, dest_square, promotion);
 
-        return (min_eval, move_result);
+    if MoveGen::new_legal(board).any(|legal_move| legal_move == chess_move) {
+        Ok(chess_move)
+    } else {
+        Err(illegal())
     }
 }
 
-fn evaluate(board: &Board, maximizing: bool) -> isize {
-    let maximizing_player = if maximizing {
-        board.side_to_move()
-    } else {
-        !board.side_to_move()
-    };
+// The static evaluation from the point of view of the side to move, as negamax wants it
+fn evaluate(board: &Board, pawn_table: &mut PawnTable) -> isize {
+    let us = board.side_to_move();
+    let them = !us;
 
-    let mut score = 0;
-    let max_castle_rights = board.castle_rights(maximizing_player);
-    let min_castle_rights = board.castle_rights(!maximizing_player);
+    let mut score = Score::default();
 
-    score += castle_rights_to_score(max_castle_rights);
-    score -= castle_rights_to_score(min_castle_rights);
-
-    let max_pieces = board.color_combined(maximizing_player);
-    let min_pieces = board.color_combined(!maximizing_player);
+    // Castling is only worth something while there's an attack on the king to hide from
+    score += Score::new(castle_rights_to_score(board.castle_rights(us)), 0);
+    score -= Score::new(castle_rights_to_score(board.castle_rights(them)), 0);
 
     for (piece, pst) in
         std::array::from_fn::<(Piece, PieceSquareTable), 6, _>(|i| (ALL_PIECES[i], ALL_PSTS[i]))
     {
-        let max_bit_board = board.pieces(piece) & max_pieces;
+        let material = Score::new(piece_to_score(piece), piece_to_score(piece));
+
+        let our_bit_board = board.pieces(piece) & board.color_combined(us);
 
-        score += max_bit_board.popcnt() as isize * piece_to_score(piece);
+        score += material * our_bit_board.popcnt() as isize;
 
-        score += pst.to_score(&max_bit_board);
+        score += pst.to_score(&our_bit_board, us);
 
-        let min_bit_board = board.pieces(piece) & min_pieces;
+        let their_bit_board = board.pieces(piece) & board.color_combined(them);
 
-        score -= min_bit_board.popcnt() as isize * piece_to_score(piece);
+        score -= material * their_bit_board.popcnt() as isize;
 
-        score -= pst.to_score(&min_bit_board);
+        score -= pst.to_score(&their_bit_board, them);
     }
 
-    score
+    score += pawn_table.score(board, us);
+    score += mobility(board, us) - mobility(board, them);
+
+    score.taper(game_phase(board))
+}
+
+// How much of the middlegame is left, from MAX_PHASE with every piece on the board down to 0 with only pawns and kings
+fn game_phase(board: &Board) -> isize {
+    let phase: isize = ALL_PIECES
+        .iter()
+        .map(|&piece| board.pieces(piece).popcnt() as isize * piece_phase(piece))
+        .sum();
+
+    // Promotions can put more material on the board than the game started with
+    phase.min(MAX_PHASE)
+}
+
+fn piece_phase(piece: Piece) -> isize {
+    match piece {
+        Piece::Pawn | Piece::King => 0,
+        Piece::Knight | Piece::Bishop => 1,
+        Piece::Rook => 2,
+        Piece::Queen => 4,
+    }
 }
 
 fn castle_rights_to_score(rights: CastleRights) -> isize {
@@ -508,3 +802,359 @@ fn piece_to_score(piece: Piece) -> isize {
         Piece::King => 20000,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    // The same position with the colors swapped: the ranks mirrored, the pieces and castling rights
+    // changing hands, and the other side to move
+    fn flip_colors(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+
+        let swap_case = |text: &str| -> String {
+            text.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+
+        let placement: Vec<&str> = fields[0].split('/').rev().collect();
+        let side_to_move = if fields[1] == "w" { "b" } else { "w" };
+
+        // Uppercase castling rights still have to come first
+        let mut castling: Vec<char> = swap_case(fields[2]).chars().collect();
+        castling.sort_by_key(|c| (c.is_ascii_lowercase(), *c != 'K' && *c != 'k'));
+        let castling: String = castling.into_iter().collect();
+
+        let en_passant = match fields[3] {
+            "-" => "-".to_string(),
+            square => square
+                .chars()
+                .map(|c| match c {
+                    '3' => '6',
+                    '6' => '3',
+                    c => c,
+                })
+                .collect(),
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            swap_case(&placement.join("/")),
+            side_to_move,
+            castling,
+            en_passant,
+            fields[4],
+            fields[5]
+        )
+    }
+
+    fn assert_symmetric(fen: &str) {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let flipped = Board::from_str(&flip_colors(fen)).expect("Valid flipped FEN");
+
+        let mut pawn_table = PawnTable::default();
+
+        assert_eq!(
+            evaluate(&board, &mut pawn_table),
+            evaluate(&flipped, &mut pawn_table),
+            "{fen}"
+        );
+    }
+
+    fn chess_move(notation: &str) -> ChessMove {
+        ChessMove::from_str(notation).expect("Valid move")
+    }
+
+    fn setoption(engine: &mut Engine, arguments: &str) -> Result<(), EngineError> {
+        engine.setoption(&mut arguments.split_whitespace())
+    }
+
+    #[test]
+    fn setoption_reads_names_with_spaces() {
+        let mut engine = Engine::default();
+
+        setoption(&mut engine, "name Move Overhead value 100").expect("Known option");
+        assert_eq!(engine.options.spin(options::MOVE_OVERHEAD), 100);
+
+        // Names aren't case sensitive, and neither is the value keyword
+        setoption(&mut engine, "name move overhead VALUE 250").expect("Known option");
+        assert_eq!(engine.options.spin(options::MOVE_OVERHEAD), 250);
+    }
+
+    #[test]
+    fn setoption_splits_the_name_from_a_value_with_spaces() {
+        let words = ["Book", "File", "value", "C:\\My", "Books\\book.bin"];
+
+        assert_eq!(
+            split_setoption(&words, |_| false),
+            (
+                "Book File".to_string(),
+                Some("C:\\My Books\\book.bin".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn setoption_finds_a_name_with_the_word_value_in_it() {
+        let words = ["Value", "Weight", "value", "3"];
+
+        assert_eq!(
+            split_setoption(&words, |name| name == "Value Weight"),
+            ("Value Weight".to_string(), Some("3".to_string()))
+        );
+
+        // The value itself can have the word value in it too
+        let words = ["Name", "value", "a", "value"];
+
+        assert_eq!(
+            split_setoption(&words, |name| name == "Name"),
+            ("Name".to_string(), Some("a value".to_string()))
+        );
+    }
+
+    #[test]
+    fn setoption_needs_a_value_except_for_buttons() {
+        let mut engine = Engine::default();
+
+        assert!(matches!(
+            setoption(&mut engine, "name Move Overhead"),
+            Err(EngineError::InvalidOptionValue(_, None))
+        ));
+        assert!(matches!(
+            setoption(&mut engine, "name Move Overhead value fast"),
+            Err(EngineError::InvalidOptionValue(_, Some(_)))
+        ));
+        assert!(setoption(&mut engine, "name Clear Hash").is_ok());
+    }
+
+    #[test]
+    fn setoption_rejects_unknown_options() {
+        let mut engine = Engine::default();
+
+        assert!(matches!(
+            setoption(&mut engine, "name Time Usage value Normal"),
+            Err(EngineError::UnknownOption(name)) if name == "Time Usage"
+        ));
+        assert!(matches!(
+            setoption(&mut engine, "value 3"),
+            Err(EngineError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn resizing_the_hash_stops_a_running_search() {
+        let mut engine = Engine::default();
+
+        engine
+            .position(&mut "startpos".split_whitespace())
+            .expect("Valid position");
+        engine
+            .go(&mut "infinite".split_whitespace())
+            .expect("Search started");
+
+        // The search thread holds the table until it's stopped, this would hang otherwise
+        setoption(&mut engine, "name Hash value 2").expect("Known option");
+        setoption(&mut engine, "name Clear Hash").expect("Known option");
+
+        assert!(engine.search_thread.is_none());
+    }
+
+    #[test]
+    fn a_search_without_moves_doesnt_fail_the_next_command() {
+        let mut engine = Engine::default();
+
+        position(&mut engine, "fen 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").expect("Valid position");
+        engine
+            .go(&mut "depth 3".split_whitespace())
+            .expect("Search started");
+        engine
+            .stop()
+            .expect("Checkmate is answered with a null move");
+
+        position(&mut engine, "startpos").expect("Valid position");
+        engine
+            .go(&mut "depth 2".split_whitespace())
+            .expect("Search started");
+        engine.stop().expect("Search finished");
+    }
+
+    fn position(engine: &mut Engine, arguments: &str) -> Result<(), EngineError> {
+        engine.position(&mut arguments.split_whitespace())
+    }
+
+    #[test]
+    fn parse_move_reads_castling_and_promotions() {
+        let castling = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").expect("Valid FEN");
+        let promotion = Board::from_str("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").expect("Valid FEN");
+
+        assert_eq!(
+            parse_move(&castling, "e1g1", 1).expect("Legal move"),
+            chess_move("e1g1")
+        );
+        assert_eq!(
+            parse_move(&castling, "e1c1", 1).expect("Legal move"),
+            chess_move("e1c1")
+        );
+        assert_eq!(
+            parse_move(&promotion, "b7b8n", 1)
+                .expect("Legal move")
+                .get_promotion(),
+            Some(Piece::Knight)
+        );
+
+        // A pawn can't reach the last rank without promoting
+        assert!(parse_move(&promotion, "b7b8", 1).is_err());
+    }
+
+    #[test]
+    fn parse_move_rejects_badly_formed_moves() {
+        let board = Board::default();
+
+        for notation in [
+            "E2E4", "e2e4Q", "e7e8k", "e2", "e2e4q5", "z2e4", "e2e5", "e1g1", "é2e4",
+        ] {
+            assert!(
+                matches!(
+                    parse_move(&board, notation, 3),
+                    Err(EngineError::IllegalMove(ref illegal, 3)) if illegal == notation
+                ),
+                "{notation}"
+            );
+        }
+    }
+
+    #[test]
+    fn an_illegal_move_leaves_no_position_behind() {
+        let mut engine = Engine::default();
+
+        position(&mut engine, "startpos moves e2e4 e7e5").expect("Legal moves");
+        assert!(engine.current_board.is_some());
+
+        assert!(matches!(
+            position(&mut engine, "startpos moves e2e4 e2e4"),
+            Err(EngineError::IllegalMove(ref illegal, 2)) if illegal == "e2e4"
+        ));
+        assert!(engine.current_board.is_none());
+        assert!(engine.moves.is_empty());
+        assert!(matches!(
+            engine.go(&mut "depth 1".split_whitespace()),
+            Err(EngineError::InvalidCommand(_))
+        ));
+
+        // A broken FEN doesn't leave the old position either
+        position(&mut engine, "startpos").expect("Valid position");
+        assert!(position(&mut engine, "fen 4k3/8/8/8/8/8/8/4K3 w - - x 1").is_err());
+        assert!(engine.current_board.is_none());
+    }
+
+    #[test]
+    fn a_repeated_search_keeps_the_whole_line_and_a_ponder_move() {
+        let board = Board::default().make_move_new(chess_move("e2e4"));
+        let go_options = GoOptions {
+            depth: 6,
+            ..GoOptions::default()
+        };
+        let options = Options::default();
+        let mut transposition_table = TranspositionTable::new(1);
+
+        // The second search finds every position of the first one in the table, hash cutoffs
+        // there mustn't cut the principal variation short
+        for _ in 0..2 {
+            let mut search = Search::new(
+                board,
+                &go_options,
+                &options,
+                Signals::default(),
+                History::default(),
+                &mut transposition_table,
+            );
+            let principal_variation = search.iterative_deepening().expect("Writing to stdout");
+
+            assert!(principal_variation.len() >= go_options.depth);
+            assert!(ponder_move(&board, &principal_variation, &transposition_table).is_some());
+        }
+    }
+
+    #[test]
+    fn the_ponder_move_falls_back_to_the_hash_move() {
+        let board = Board::default();
+        let after_e4 = board.make_move_new(chess_move("e2e4"));
+        let mut transposition_table = TranspositionTable::new(1);
+
+        assert_eq!(
+            ponder_move(&board, &[chess_move("e2e4")], &transposition_table),
+            None
+        );
+
+        transposition_table.store(
+            after_e4.get_hash(),
+            1,
+            tt::Bound::Exact,
+            0,
+            Some(chess_move("e7e5")),
+        );
+
+        assert_eq!(
+            ponder_move(&board, &[chess_move("e2e4")], &transposition_table),
+            Some(chess_move("e7e5"))
+        );
+    }
+
+    #[test]
+    fn flipping_the_colors_keeps_the_evaluation() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 0 10",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+            "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+            "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+        ] {
+            assert_symmetric(fen);
+        }
+    }
+
+    #[test]
+    fn advanced_pawns_are_worth_more_for_both_colors() {
+        let white = Board::from_str("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").expect("Valid FEN");
+        let white_behind = Board::from_str("4k3/8/8/8/8/3P4/8/4K3 w - - 0 1").expect("Valid FEN");
+        let black = Board::from_str("4k3/8/8/8/3p4/8/8/4K3 b - - 0 1").expect("Valid FEN");
+        let black_behind = Board::from_str("4k3/8/3p4/8/8/8/8/4K3 b - - 0 1").expect("Valid FEN");
+
+        let mut pawn_table = PawnTable::default();
+
+        assert!(evaluate(&white, &mut pawn_table) > evaluate(&white_behind, &mut pawn_table));
+        assert!(evaluate(&black, &mut pawn_table) > evaluate(&black_behind, &mut pawn_table));
+    }
+
+    #[test]
+    fn the_phase_blends_the_middlegame_into_the_endgame() {
+        let score = Score::new(100, -40);
+        let start = Board::default();
+        let pawn_ending = Board::from_str("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").expect("Valid FEN");
+
+        assert_eq!(game_phase(&start), MAX_PHASE);
+        assert_eq!(game_phase(&pawn_ending), 0);
+        assert_eq!(score.taper(MAX_PHASE), 100);
+        assert_eq!(score.taper(MAX_PHASE / 2), 30);
+        assert_eq!(score.taper(0), -40);
+    }
+
+    #[test]
+    fn the_king_comes_to_the_center_in_a_pawn_ending() {
+        let cornered = Board::from_str("4k3/8/8/8/8/8/4P3/K7 w - - 0 1").expect("Valid FEN");
+        let centralized = Board::from_str("4k3/8/8/8/3K4/8/4P3/8 w - - 0 1").expect("Valid FEN");
+
+        let mut pawn_table = PawnTable::default();
+
+        assert!(evaluate(&centralized, &mut pawn_table) > evaluate(&cornered, &mut pawn_table));
+    }
+}
@@ -0,0 +1,272 @@
+use {
+    super::see::see,
+    chess::{Board, ChessMove, EMPTY, MoveGen, Piece},
+};
+
+// Quiet moves that caused a beta cutoff at the same ply, they often refute the sibling positions too
+pub type Killers = [Option<ChessMove>; 2];
+
+// Keeps the history scores from growing past what fits next to the other move scores
+const MAX_HISTORY: isize = 1 << 20;
+
+// Scores that put promotions and killers in front of the quiet moves sorted by history
+const PROMOTION_SCORE: isize = 3 * MAX_HISTORY;
+const FIRST_KILLER_SCORE: isize = 2 * MAX_HISTORY;
+const SECOND_KILLER_SCORE: isize = 2 * MAX_HISTORY - 1;
+
+// How often quiet moves caused a cutoff, indexed by side to move, from square and to square
+#[derive(Clone, Debug)]
+pub struct HistoryTable {
+    table: Vec<isize>,
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self {
+            table: vec![0; 2 * 64 * 64],
+        }
+    }
+}
+
+impl HistoryTable {
+    pub fn get(&self, board: &Board, chess_move: ChessMove) -> isize {
+        self.table[Self::index(board, chess_move)]
+    }
+
+    // Deeper cutoffs are worth more, since they saved the search more work
+    pub fn reward(&mut self, board: &Board, chess_move: ChessMove, depth: usize) {
+        let index = Self::index(board, chess_move);
+        let bonus = (depth * depth) as isize;
+
+        self.table[index] = (self.table[index] + bonus).min(MAX_HISTORY);
+    }
+
+    fn index(board: &Board, chess_move: ChessMove) -> usize {
+        board.side_to_move().to_index() * 64 * 64
+            + chess_move.get_source().to_index() * 64
+            + chess_move.get_dest().to_index()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stage {
+    HashMove,
+    GenerateCaptures,
+    Captures,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+}
+
+// Hands out the legal moves of a position, the ones most likely to cause a cutoff first:
+// the hash move, captures by MVV-LVA, promotions, killers, quiet moves by history and then the captures
+// that lose material. Captures are generated before the quiet moves, so a cutoff early on saves generating the rest
+pub struct MovePicker {
+    move_gen: MoveGen,
+    legal_moves: usize,
+    stage: Stage,
+    hash_move: Option<ChessMove>,
+    killers: Killers,
+    // The moves of the current stage with their scores, sorted so the best one is popped first
+    moves: Vec<(ChessMove, isize)>,
+    // Captures the static exchange evaluation says lose material, they're only tried after the quiet moves
+    bad_captures: Vec<ChessMove>,
+}
+
+impl MovePicker {
+    pub fn new(board: &Board, hash_move: Option<ChessMove>, killers: Killers) -> Self {
+        let move_gen = MoveGen::new_legal(board);
+
+        Self {
+            legal_moves: move_gen.len(),
+            move_gen,
+            stage: Stage::HashMove,
+            // The hash move could come from another position with the same key
+            hash_move: hash_move.filter(|&chess_move| board.legal(chess_move)),
+            killers,
+            moves: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    pub fn has_legal_moves(&self) -> bool {
+        self.legal_moves > 0
+    }
+
+    pub fn next(&mut self, board: &Board, history: &HistoryTable) -> Option<ChessMove> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+
+                    if self.hash_move.is_some() {
+                        return self.hash_move;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.move_gen
+                        .set_iterator_mask(*board.color_combined(!board.side_to_move()));
+
+                    self.moves = (&mut self.move_gen)
+                        .map(|chess_move| (chess_move, mvv_lva(board, chess_move)))
+                        .collect();
+                    self.moves.sort_unstable_by_key(|&(_, score)| score);
+
+                    self.stage = Stage::Captures;
+                }
+                Stage::GenerateQuiets => {
+                    self.move_gen.set_iterator_mask(!EMPTY);
+
+                    let killers = self.killers;
+
+                    self.moves = (&mut self.move_gen)
+                        .map(|chess_move| {
+                            (chess_move, quiet_score(board, chess_move, killers, history))
+                        })
+                        .collect();
+                    self.moves.sort_unstable_by_key(|&(_, score)| score);
+
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Captures => match self.moves.pop() {
+                    Some((chess_move, _)) if Some(chess_move) == self.hash_move => (),
+                    Some((chess_move, _)) if see(board, chess_move) < 0 => {
+                        self.bad_captures.push(chess_move)
+                    }
+                    Some((chess_move, _)) => return Some(chess_move),
+                    None => self.stage = Stage::GenerateQuiets,
+                },
+                Stage::Quiets => match self.moves.pop() {
+                    Some((chess_move, _)) if Some(chess_move) == self.hash_move => (),
+                    Some((chess_move, _)) => return Some(chess_move),
+                    None => {
+                        // Popped from the back, so the best of the bad captures has to go last
+                        self.bad_captures.reverse();
+                        self.stage = Stage::BadCaptures;
+                    }
+                },
+                Stage::BadCaptures => return self.bad_captures.pop(),
+            }
+        }
+    }
+}
+
+fn quiet_score(
+    board: &Board,
+    chess_move: ChessMove,
+    killers: Killers,
+    history: &HistoryTable,
+) -> isize {
+    match chess_move.get_promotion() {
+        Some(piece) => PROMOTION_SCORE + piece_order(piece),
+        None if Some(chess_move) == killers[0] => FIRST_KILLER_SCORE,
+        None if Some(chess_move) == killers[1] => SECOND_KILLER_SCORE,
+        None => history.get(board, chess_move),
+    }
+}
+
+// Most valuable victim first, and among those the least valuable attacker first
+pub fn mvv_lva(board: &Board, chess_move: ChessMove) -> isize {
+    let victim = board.piece_on(chess_move.get_dest()).map_or(0, piece_order);
+    let attacker = board
+        .piece_on(chess_move.get_source())
+        .map_or(0, piece_order);
+    let promotion = chess_move.get_promotion().map_or(0, piece_order);
+
+    (victim + promotion) * 8 - attacker
+}
+
+// Captures and promotions change the material, everything else is a quiet move
+pub fn is_quiet(board: &Board, chess_move: ChessMove) -> bool {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+
+    // A pawn moving to another file without capturing what's on the square took en passant
+    let en_passant =
+        board.piece_on(source) == Some(Piece::Pawn) && source.get_file() != dest.get_file();
+
+    chess_move.get_promotion().is_none() && board.piece_on(dest).is_none() && !en_passant
+}
+
+fn piece_order(piece: Piece) -> isize {
+    piece.to_index() as isize + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    // Pawn and knight can take the queen on d5, the queen can only take a defended pawn on h6,
+    // and the pawn on b7 promotes
+    const FEN: &str = "4k3/1P4p1/7p/3q4/4P3/2N5/7Q/4K3 w - - 0 1";
+
+    fn chess_move(notation: &str) -> ChessMove {
+        ChessMove::from_str(notation).expect("Valid move")
+    }
+
+    fn picked_moves(
+        board: &Board,
+        hash_move: Option<ChessMove>,
+        killers: Killers,
+        history: &HistoryTable,
+    ) -> Vec<ChessMove> {
+        let mut move_picker = MovePicker::new(board, hash_move, killers);
+
+        std::iter::from_fn(|| move_picker.next(board, history)).collect()
+    }
+
+    #[test]
+    fn picks_the_moves_stage_by_stage() {
+        let board = Board::from_str(FEN).expect("Valid FEN");
+        let mut history = HistoryTable::default();
+        history.reward(&board, chess_move("e1f1"), 10);
+
+        let moves = picked_moves(
+            &board,
+            Some(chess_move("e1f2")),
+            [Some(chess_move("c3b5")), Some(chess_move("h2h4"))],
+            &history,
+        );
+
+        // The hash move, good captures with the least valuable attacker first, promotions with the most
+        // valuable piece first, the killers and then the quiet move with history
+        let expected = [
+            "e1f2", "e4d5", "c3d5", "b7b8q", "b7b8r", "b7b8b", "b7b8n", "c3b5", "h2h4", "e1f1",
+        ]
+        .map(chess_move);
+
+        assert_eq!(moves[..expected.len()], expected);
+
+        // Losing the queen for a pawn comes after every quiet move
+        assert_eq!(moves.last(), Some(&chess_move("h2h6")));
+    }
+
+    #[test]
+    fn picks_every_legal_move_once() {
+        let board = Board::from_str(FEN).expect("Valid FEN");
+        let mut legal_moves: Vec<ChessMove> = MoveGen::new_legal(&board).collect();
+        legal_moves.sort_by_key(|chess_move| chess_move.to_string());
+
+        // The hash move is also a capture, a killer or both, and the second killer isn't legal here
+        for (hash_move, killers) in [
+            (chess_move("c3d5"), [Some(chess_move("c3d5")), None]),
+            (
+                chess_move("c3b5"),
+                [Some(chess_move("c3b5")), Some(chess_move("h2h4"))],
+            ),
+            (
+                chess_move("h2h6"),
+                [Some(chess_move("h2h4")), Some(chess_move("a1a2"))],
+            ),
+        ] {
+            let mut moves =
+                picked_moves(&board, Some(hash_move), killers, &HistoryTable::default());
+
+            assert_eq!(moves[0], hash_move);
+
+            moves.sort_by_key(|chess_move| chess_move.to_string());
+
+            assert_eq!(moves, legal_moves);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use {
+    super::{
+        EngineError,
+        tt::{DEFAULT_HASH_MEGABYTES, MAX_HASH_MEGABYTES, MIN_HASH_MEGABYTES},
+    },
+    std::fmt::{Display, Formatter, Result as FmtResult},
+};
+
+pub const HASH: &str = "Hash";
+pub const CLEAR_HASH: &str = "Clear Hash";
+pub const PONDER: &str = "Ponder";
+pub const MULTI_PV: &str = "MultiPV";
+pub const MOVE_OVERHEAD: &str = "Move Overhead";
+pub const FUTILITY_MARGIN: &str = "Futility Margin";
+pub const REVERSE_FUTILITY_MARGIN: &str = "Reverse Futility Margin";
+pub const RAZORING_MARGIN: &str = "Razoring Margin";
+
+// The types of option the UCI protocol knows, with the limits the GUI is told about
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionKind {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    // No combo or string option is registered yet, but the GUI has to be able to set them when there is
+    #[allow(dead_code)]
+    Combo {
+        default: &'static str,
+        variants: &'static [&'static str],
+    },
+    Button,
+    #[allow(dead_code)]
+    String {
+        default: &'static str,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionValue {
+    Check(bool),
+    Spin(i64),
+    Combo(&'static str),
+    // Buttons don't hold anything, pressing them is all there is
+    Button,
+    String(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct EngineOption {
+    name: &'static str,
+    kind: OptionKind,
+    value: OptionValue,
+}
+
+impl EngineOption {
+    pub fn new(name: &'static str, kind: OptionKind) -> Self {
+        let value = match &kind {
+            OptionKind::Check { default } => OptionValue::Check(*default),
+            OptionKind::Spin { default, .. } => OptionValue::Spin(*default),
+            OptionKind::Combo { default, .. } => OptionValue::Combo(default),
+            OptionKind::Button => OptionValue::Button,
+            OptionKind::String { default } => OptionValue::String(default.to_string()),
+        };
+
+        Self { name, kind, value }
+    }
+
+    fn parse(&self, value: &str) -> Option<OptionValue> {
+        match &self.kind {
+            OptionKind::Check { .. } => match value.to_lowercase().as_str() {
+                "true" => Some(OptionValue::Check(true)),
+                "false" => Some(OptionValue::Check(false)),
+                _ => None,
+            },
+            OptionKind::Spin { min, max, .. } => value
+                .parse()
+                .ok()
+                .filter(|value| (min..=max).contains(&value))
+                .map(OptionValue::Spin),
+            OptionKind::Combo { variants, .. } => variants
+                .iter()
+                .find(|variant| variant.eq_ignore_ascii_case(value))
+                .map(|variant| OptionValue::Combo(variant)),
+            OptionKind::Button => Some(OptionValue::Button),
+            // The GUI sends <empty> when the user cleared the field
+            OptionKind::String { .. } => Some(OptionValue::String(match value {
+                "<empty>" => String::new(),
+                value => value.to_string(),
+            })),
+        }
+    }
+}
+
+// How the option is advertised to the GUI after uci
+impl Display for EngineOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "option name {} type ", self.name)?;
+
+        match &self.kind {
+            OptionKind::Check { default } => write!(f, "check default {}", default),
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            }
+            OptionKind::Combo { default, variants } => {
+                write!(f, "combo default {}", default)?;
+
+                for variant in variants.iter() {
+                    write!(f, " var {}", variant)?;
+                }
+
+                Ok(())
+            }
+            OptionKind::Button => write!(f, "button"),
+            OptionKind::String { default } => match *default {
+                "" => write!(f, "string default <empty>"),
+                default => write!(f, "string default {}", default),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    options: Vec<EngineOption>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            options: vec![
+                EngineOption::new(
+                    HASH,
+                    OptionKind::Spin {
+                        default: DEFAULT_HASH_MEGABYTES as i64,
+                        min: MIN_HASH_MEGABYTES as i64,
+                        max: MAX_HASH_MEGABYTES as i64,
+                    },
+                ),
+                EngineOption::new(CLEAR_HASH, OptionKind::Button),
+                // Only there so the GUI knows it can send us go ponder
+                EngineOption::new(PONDER, OptionKind::Check { default: false }),
+                EngineOption::new(
+                    MULTI_PV,
+                    OptionKind::Spin {
+                        default: 1,
+                        min: 1,
+                        max: 256,
+                    },
+                ),
+                EngineOption::new(
+                    MOVE_OVERHEAD,
+                    OptionKind::Spin {
+                        default: 30,
+                        min: 0,
+                        max: 5000,
+                    },
+                ),
+                // Centipawns per ply of depth, exposed so they can be tuned without a rebuild
+                EngineOption::new(
+                    FUTILITY_MARGIN,
+                    OptionKind::Spin {
+                        default: 150,
+                        min: 0,
+                        max: 1000,
+                    },
+                ),
+                EngineOption::new(
+                    REVERSE_FUTILITY_MARGIN,
+                    OptionKind::Spin {
+                        default: 100,
+                        min: 0,
+                        max: 1000,
+                    },
+                ),
+                EngineOption::new(
+                    RAZORING_MARGIN,
+                    OptionKind::Spin {
+                        default: 250,
+                        min: 0,
+                        max: 1000,
+                    },
+                ),
+            ],
+        }
+    }
+}
+
+impl Options {
+    pub fn iter(&self) -> impl Iterator<Item = &EngineOption> {
+        self.options.iter()
+    }
+
+    // Option names aren't case sensitive, the name it was registered with is returned
+    pub fn find(&self, name: &str) -> Option<&'static str> {
+        self.options
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .map(|option| option.name)
+    }
+
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<&OptionValue, EngineError> {
+        let option = self
+            .options
+            .iter_mut()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| EngineError::UnknownOption(name.to_string()))?;
+
+        let invalid =
+            || EngineError::InvalidOptionValue(option.name.to_string(), value.map(str::to_string));
+
+        option.value = match (&option.kind, value) {
+            (OptionKind::Button, _) => OptionValue::Button,
+            (_, Some(value)) => option.parse(value).ok_or_else(invalid)?,
+            (_, None) => return Err(invalid()),
+        };
+
+        Ok(&option.value)
+    }
+
+    pub fn spin(&self, name: &str) -> i64 {
+        match self.value(name) {
+            OptionValue::Spin(value) => *value,
+            _ => panic!("{} isn't a spin option", name),
+        }
+    }
+
+    fn value(&self, name: &str) -> &OptionValue {
+        &self
+            .options
+            .iter()
+            .find(|option| option.name == name)
+            .unwrap_or_else(|| panic!("{} isn't a registered option", name))
+            .value
+    }
+}
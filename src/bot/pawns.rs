@@ -0,0 +1,287 @@
+use {
+    super::Score,
+    chess::{
+        BitBoard, Board, Color, EMPTY, Piece, Square, get_adjacent_files, get_file,
+        get_pawn_attacks,
+    },
+};
+
+// Enough entries that the few pawn structures of a search almost never push each other out
+const PAWN_TABLE_ENTRIES: usize = 1 << 14;
+
+const DOUBLED_PAWN: Score = Score::new(-10, -25);
+const ISOLATED_PAWN: Score = Score::new(-12, -15);
+const BACKWARD_PAWN: Score = Score::new(-8, -12);
+const CONNECTED_PAWN: Score = Score::new(8, 6);
+
+// By the rank the pawn is on seen from its own side, passed pawns matter most once the pieces come off
+const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(0, 10),
+    Score::new(5, 15),
+    Score::new(10, 25),
+    Score::new(20, 45),
+    Score::new(35, 75),
+    Score::new(60, 120),
+    Score::new(0, 0),
+];
+
+// A passed pawn nothing stands in front of on the way to promotion, on top of the passed pawn bonus
+const FREE_PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(0, 5),
+    Score::new(5, 10),
+    Score::new(10, 20),
+    Score::new(15, 40),
+    Score::new(25, 70),
+    Score::new(0, 0),
+];
+
+const BLOCKED_PASSED_PAWN: Score = Score::new(-5, -15);
+
+// Random keys for a pawn of each color on each square, fixed so the keys are the same every run
+const PAWN_KEYS: [[u64; 64]; 2] = pawn_keys();
+
+const fn pawn_keys() -> [[u64; 64]; 2] {
+    let mut keys = [[0; 64]; 2];
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut index = 0;
+
+    // SplitMix64, good enough to spread the keys over every bit
+    while index < 2 * 64 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut key = state;
+        key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        keys[index / 64][index % 64] = key ^ (key >> 31);
+        index += 1;
+    }
+
+    keys
+}
+
+// A Zobrist key of just the pawns. The chess crate has a pawn hash too, but it's always 0
+pub fn pawn_key(board: &Board) -> u64 {
+    let mut key = 0;
+
+    for color in [Color::White, Color::Black] {
+        let pawns = board.pieces(Piece::Pawn) & board.color_combined(color);
+
+        for square in pawns {
+            key ^= PAWN_KEYS[color.to_index()][square.to_index()];
+        }
+    }
+
+    key
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PawnEntry {
+    key: u64,
+    // From White's point of view
+    score: Score,
+    passed_pawns: BitBoard,
+}
+
+// The pawn structure changes far less often than the rest of the position,
+// so what only depends on the pawns is worked out once per structure and looked up after that
+#[derive(Clone, Debug)]
+pub struct PawnTable {
+    entries: Vec<Option<PawnEntry>>,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self {
+            entries: vec![None; PAWN_TABLE_ENTRIES],
+        }
+    }
+}
+
+impl PawnTable {
+    // The pawn structure terms for the given side
+    pub fn score(&mut self, board: &Board, color: Color) -> Score {
+        let entry = self.probe(board);
+        let mut score = entry.score;
+
+        // Whether a passed pawn's path is clear depends on the pieces too, so it can't be cached
+        for square in entry.passed_pawns {
+            score += passed_pawn_path(board, square);
+        }
+
+        match color {
+            Color::White => score,
+            Color::Black => Score::default() - score,
+        }
+    }
+
+    fn probe(&mut self, board: &Board) -> PawnEntry {
+        let key = pawn_key(board);
+        let index = key as usize % self.entries.len();
+
+        match self.entries[index] {
+            Some(entry) if entry.key == key => entry,
+            _ => {
+                let (score, passed_pawns) = pawn_structure(board);
+                let entry = PawnEntry {
+                    key,
+                    score,
+                    passed_pawns,
+                };
+
+                self.entries[index] = Some(entry);
+
+                entry
+            }
+        }
+    }
+}
+
+// The score of the pawns from White's point of view, and which pawns of both colors are passed
+fn pawn_structure(board: &Board) -> (Score, BitBoard) {
+    let mut score = Score::default();
+    let mut passed_pawns = EMPTY;
+
+    for color in [Color::White, Color::Black] {
+        let our_pawns = board.pieces(Piece::Pawn) & board.color_combined(color);
+        let their_pawns = board.pieces(Piece::Pawn) & board.color_combined(!color);
+        let mut side_score = Score::default();
+
+        for square in our_pawns {
+            let file = get_file(square.get_file());
+            let adjacent_files = get_adjacent_files(square.get_file());
+            let ahead = ranks_ahead(square, color);
+
+            // Counted for the pawns behind, so two pawns on a file are one doubled pawn
+            if our_pawns & file & ahead != EMPTY {
+                side_score += DOUBLED_PAWN;
+            }
+
+            let isolated = our_pawns & adjacent_files == EMPTY;
+
+            if isolated {
+                side_score += ISOLATED_PAWN;
+            }
+
+            let defended = get_pawn_attacks(square, !color, our_pawns) != EMPTY;
+            let phalanx = our_pawns & adjacent_files & rank_of(square) != EMPTY;
+
+            if defended || phalanx {
+                side_score += CONNECTED_PAWN;
+            }
+
+            // No pawns next to or behind it can come up to defend it, and its stop square is
+            // covered by an enemy pawn, so it can't safely advance to meet them either
+            if let (false, Some(stop)) = (isolated, square.forward(color)) {
+                let supporters = our_pawns & adjacent_files & !ahead;
+                let stop_attacked = get_pawn_attacks(stop, color, their_pawns) != EMPTY;
+
+                if supporters == EMPTY && stop_attacked {
+                    side_score += BACKWARD_PAWN;
+                }
+            }
+
+            if their_pawns & (file | adjacent_files) & ahead == EMPTY {
+                side_score += PASSED_PAWN[relative_rank(square, color)];
+                passed_pawns |= BitBoard::from_square(square);
+            }
+        }
+
+        score = match color {
+            Color::White => score + side_score,
+            Color::Black => score - side_score,
+        };
+    }
+
+    (score, passed_pawns)
+}
+
+// From White's point of view, a passed pawn with a piece right in front of it is stuck,
+// and one with nothing at all on the way to promotion is hard to stop
+fn passed_pawn_path(board: &Board, square: Square) -> Score {
+    let Some(color) = board.color_on(square) else {
+        return Score::default();
+    };
+
+    let path = get_file(square.get_file()) & ranks_ahead(square, color);
+    let score = match square.forward(color) {
+        Some(stop) if board.piece_on(stop).is_some() => BLOCKED_PASSED_PAWN,
+        _ if path & board.combined() == EMPTY => FREE_PASSED_PAWN[relative_rank(square, color)],
+        _ => Score::default(),
+    };
+
+    match color {
+        Color::White => score,
+        Color::Black => Score::default() - score,
+    }
+}
+
+// Every square on the ranks in front of the square, seen from the given side
+fn ranks_ahead(square: Square, color: Color) -> BitBoard {
+    let rank = square.get_rank().to_index() as u32;
+
+    match color {
+        Color::White => BitBoard(u64::MAX.checked_shl(8 * (rank + 1)).unwrap_or(0)),
+        Color::Black => BitBoard((1 << (8 * rank)) - 1),
+    }
+}
+
+fn rank_of(square: Square) -> BitBoard {
+    BitBoard(0xFF << (8 * square.get_rank().to_index()))
+}
+
+fn relative_rank(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => square.get_rank().to_index(),
+        Color::Black => 7 - square.get_rank().to_index(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    fn score_of(fen: &str) -> Score {
+        let board = Board::from_str(fen).expect("Valid FEN");
+
+        PawnTable::default().score(&board, Color::White)
+    }
+
+    #[test]
+    fn the_pawn_key_only_depends_on_the_pawns() {
+        let board = Board::from_str("4k3/pp6/8/8/8/8/PP6/4K1N1 w - - 0 1").expect("Valid FEN");
+        let knight_moved =
+            Board::from_str("4k3/pp6/8/8/8/5N2/PP6/4K3 b - - 1 1").expect("Valid FEN");
+        let pawn_moved =
+            Board::from_str("4k3/pp6/8/8/8/P7/1P6/4K1N1 b - - 0 1").expect("Valid FEN");
+
+        assert_eq!(pawn_key(&board), pawn_key(&knight_moved));
+        assert_ne!(pawn_key(&board), pawn_key(&pawn_moved));
+    }
+
+    #[test]
+    fn doubled_and_isolated_pawns_are_weaknesses() {
+        let healthy = score_of("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1");
+        let doubled = score_of("4k3/8/8/8/8/1P6/PP6/4K3 w - - 0 1");
+        let isolated = score_of("4k3/8/8/8/8/8/P1P5/4K3 w - - 0 1");
+
+        assert!(doubled.end_game < healthy.end_game);
+        assert!(isolated.end_game < healthy.end_game);
+    }
+
+    #[test]
+    fn passed_pawns_are_worth_more_further_up_and_with_a_free_path() {
+        let passed = score_of("4k3/8/8/8/3P4/8/8/4K3 w - - 0 1");
+        let advanced = score_of("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1");
+        let blocked = score_of("4k3/8/3n4/3P4/8/8/8/4K3 w - - 0 1");
+        let free = score_of("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1");
+        let stopped = score_of("4k3/3p4/8/3P4/8/8/8/4K3 w - - 0 1");
+
+        assert!(advanced.end_game > passed.end_game);
+        assert!(free.end_game > blocked.end_game);
+        assert!(free.end_game > stopped.end_game);
+    }
+}
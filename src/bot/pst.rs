@@ -1,5 +1,8 @@
-use chess::BitBoard;
 pub use pst_unformatted::*;
+use {
+    super::Score,
+    chess::{BitBoard, Color},
+};
 
 pub const ALL_PSTS: [PieceSquareTable; 6] = [
     PAWN_PST, KNIGHT_PST, BISHOP_PST, ROOK_PST, QUEEN_PST, KING_PST,
@@ -8,7 +11,7 @@ pub const ALL_PSTS: [PieceSquareTable; 6] = [
 #[rustfmt::skip]
 mod pst_unformatted {
     pub const PAWN_PST: PieceSquareTable = PieceSquareTable {
-        table: [
+        middle_game: [
              0,  0,  0,  0,  0,  0,  0,  0,
             50, 50, 50, 50, 50, 50, 50, 50,
             10, 10, 20, 30, 30, 20, 10, 10,
@@ -18,10 +21,20 @@ mod pst_unformatted {
              5, 10, 10,-20,-20, 10, 10,  5,
              0,  0,  0,  0,  0,  0,  0,  0,
         ],
+        end_game: [
+             0,  0,  0,  0,  0,  0,  0,  0,
+            80, 80, 80, 80, 80, 80, 80, 80,
+            50, 50, 50, 50, 50, 50, 50, 50,
+            30, 30, 30, 30, 30, 30, 30, 30,
+            20, 20, 20, 20, 20, 20, 20, 20,
+            10, 10, 10, 10, 10, 10, 10, 10,
+            10, 10, 10, 10, 10, 10, 10, 10,
+             0,  0,  0,  0,  0,  0,  0,  0,
+        ],
     };
 
     pub const KNIGHT_PST: PieceSquareTable = PieceSquareTable {
-        table: [
+        middle_game: [
             -50,-40,-30,-30,-30,-30,-40,-50,
             -40,-20,  0,  0,  0,  0,-20,-40,
             -30,  0, 10, 15, 15, 10,  0,-30,
@@ -31,10 +44,20 @@ mod pst_unformatted {
             -40,-20,  0,  5,  5,  0,-20,-40,
             -50,-40,-30,-30,-30,-30,-40,-50,
         ],
+        end_game: [
+            -50,-40,-30,-30,-30,-30,-40,-50,
+            -40,-20,-10, -5, -5,-10,-20,-40,
+            -30,-10,  5, 10, 10,  5,-10,-30,
+            -30, -5, 10, 15, 15, 10, -5,-30,
+            -30, -5, 10, 15, 15, 10, -5,-30,
+            -30,-10,  5, 10, 10,  5,-10,-30,
+            -40,-20,-10, -5, -5,-10,-20,-40,
+            -50,-40,-30,-30,-30,-30,-40,-50,
+        ],
     };
 
     pub const BISHOP_PST: PieceSquareTable = PieceSquareTable {
-        table: [
+        middle_game: [
             -20,-10,-10,-10,-10,-10,-10,-20,
             -10,  0,  0,  0,  0,  0,  0,-10,
             -10,  0,  5, 10, 10,  5,  0,-10,
@@ -44,10 +67,20 @@ mod pst_unformatted {
             -10,  5,  0,  0,  0,  0,  5,-10,
             -20,-10,-10,-10,-10,-10,-10,-20,
         ],
+        end_game: [
+            -20,-10,-10,-10,-10,-10,-10,-20,
+            -10,  0,  0,  0,  0,  0,  0,-10,
+            -10,  0,  5,  5,  5,  5,  0,-10,
+            -10,  0,  5, 10, 10,  5,  0,-10,
+            -10,  0,  5, 10, 10,  5,  0,-10,
+            -10,  0,  5,  5,  5,  5,  0,-10,
+            -10,  0,  0,  0,  0,  0,  0,-10,
+            -20,-10,-10,-10,-10,-10,-10,-20,
+        ],
     };
 
     pub const ROOK_PST: PieceSquareTable = PieceSquareTable {
-        table: [
+        middle_game: [
              0,  0,  0,  0,  0,  0,  0,  0,
              5, 10, 10, 10, 10, 10, 10,  5,
             -5,  0,  0,  0,  0,  0,  0, -5,
@@ -57,10 +90,20 @@ mod pst_unformatted {
             -5,  0,  0,  0,  0,  0,  0, -5,
              0,  0,  0,  5,  5,  0,  0,  0,
         ],
+        end_game: [
+             0,  0,  0,  0,  0,  0,  0,  0,
+            10, 10, 10, 10, 10, 10, 10, 10,
+             0,  0,  0,  0,  0,  0,  0,  0,
+             0,  0,  0,  0,  0,  0,  0,  0,
+             0,  0,  0,  0,  0,  0,  0,  0,
+             0,  0,  0,  0,  0,  0,  0,  0,
+             0,  0,  0,  0,  0,  0,  0,  0,
+             0,  0,  0,  0,  0,  0,  0,  0,
+        ],
     };
 
     pub const QUEEN_PST: PieceSquareTable = PieceSquareTable {
-        table: [
+        middle_game: [
             -20,-10,-10, -5, -5,-10,-10,-20,
             -10,  0,  0,  0,  0,  0,  0,-10,
             -10,  0,  5,  5,  5,  5,  0,-10,
@@ -70,11 +113,20 @@ mod pst_unformatted {
             -10,  0,  5,  0,  0,  0,  0,-10,
             -20,-10,-10, -5, -5,-10,-10,-20,
         ],
+        end_game: [
+            -20,-10,-10, -5, -5,-10,-10,-20,
+            -10,  0,  5,  5,  5,  5,  0,-10,
+            -10,  5, 10, 10, 10, 10,  5,-10,
+             -5,  5, 10, 15, 15, 10,  5, -5,
+             -5,  5, 10, 15, 15, 10,  5, -5,
+            -10,  5, 10, 10, 10, 10,  5,-10,
+            -10,  0,  5,  5,  5,  5,  0,-10,
+            -20,-10,-10, -5, -5,-10,-10,-20,
+        ],
     };
 
-    // middle_game for now
     pub const KING_PST: PieceSquareTable = PieceSquareTable {
-        table: [
+        middle_game: [
             -30,-40,-40,-50,-50,-40,-40,-30,
             -30,-40,-40,-50,-50,-40,-40,-30,
             -30,-40,-40,-50,-50,-40,-40,-30,
@@ -84,21 +136,40 @@ mod pst_unformatted {
              20, 20,  0,  0,  0,  0, 20, 20,
              20, 30, 10,  0,  0, 10, 30, 20,
         ],
+        end_game: [
+            -50,-40,-30,-20,-20,-30,-40,-50,
+            -30,-20,-10,  0,  0,-10,-20,-30,
+            -30,-10, 20, 30, 30, 20,-10,-30,
+            -30,-10, 30, 40, 40, 30,-10,-30,
+            -30,-10, 30, 40, 40, 30,-10,-30,
+            -30,-10, 20, 30, 30, 20,-10,-30,
+            -30,-30,  0,  0,  0,  0,-30,-30,
+            -50,-30,-30,-30,-30,-30,-30,-50,
+        ],
     };
 
+    // A bonus for every square, once for the middlegame and once for the endgame
     #[derive(Clone, Copy, Debug)]
     pub struct PieceSquareTable {
-        pub table: [i16; 64],
+        pub middle_game: [i16; 64],
+        pub end_game: [i16; 64],
     }
 }
 
 impl PieceSquareTable {
-    pub fn to_score(&self, bitboard: &BitBoard) -> isize {
-        let mut result = 0;
+    // The tables are written the way White sees the board, with the eighth rank on top, while squares
+    // count up from a1. Flipping the rank reads them for White, Black reads them as they are
+    pub fn to_score(self, bitboard: &BitBoard, color: Color) -> Score {
+        let mut result = Score::default();
         bitboard.for_each(|square| {
-            let square_index = square.to_index();
-            let table_value = self.table[square_index];
-            result += table_value as isize;
+            let square_index = match color {
+                Color::White => square.to_index() ^ 56,
+                Color::Black => square.to_index(),
+            };
+            result += Score::new(
+                self.middle_game[square_index] as isize,
+                self.end_game[square_index] as isize,
+            );
         });
 
         result
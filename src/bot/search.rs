@@ -0,0 +1,1722 @@
+use {
+    super::{
+        GoOptions, evaluate,
+        mobility::attacks,
+        movepick::{HistoryTable, Killers, MovePicker, is_quiet, mvv_lva},
+        options::{FUTILITY_MARGIN, MULTI_PV, Options, RAZORING_MARGIN, REVERSE_FUTILITY_MARGIN},
+        pawns::PawnTable,
+        piece_to_score,
+        see::see,
+        time::TimeManager,
+        tt::{Bound, Entry, TranspositionTable},
+    },
+    chess::{
+        BitBoard, Board, ChessMove, EMPTY, MoveGen, Piece, get_bishop_moves, get_knight_moves,
+        get_pawn_attacks, get_rank, get_rook_moves,
+    },
+    std::{
+        io::{Error as IoError, Write, stdout},
+        mem,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::{Duration, Instant},
+    },
+};
+
+// Deepest iteration the driver will start, even without any other limit
+pub const MAX_DEPTH: usize = 64;
+
+// Bigger than any score a search can return, used for the initial window
+pub const INFINITY: isize = 1_000_000;
+
+// Getting checkmated at the root, mate in n plies is scored MATE - n so quicker mates are preferred
+pub const MATE: isize = 100_000;
+
+// Anything beyond this is a forced mate rather than an evaluation
+pub const MATE_BOUND: isize = MATE - MAX_DEPTH as isize * 4;
+
+// How many nodes are searched between each look at the clock
+const CHECKUP_INTERVAL: usize = 1024;
+
+// How often the GUI hears from a long search in between iterations
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Half the width of the first aspiration window, it doubles every time the score falls outside of it
+const ASPIRATION_WINDOW: isize = 25;
+
+// The scores of the first few iterations are still too unstable to search around them
+const ASPIRATION_DEPTH: usize = 4;
+
+// Null move searches are R = NULL_MOVE_REDUCTION + depth / 6 plies shallower than a normal move
+const NULL_MOVE_REDUCTION: usize = 3;
+
+// Below this the null move search costs about as much as it saves
+const NULL_MOVE_DEPTH: usize = 3;
+
+// From this depth on a null move cutoff is only trusted after a normal search at the reduced depth agrees
+const VERIFICATION_DEPTH: usize = 8;
+
+// Singular extensions are only tried from this depth, the search to find out costs too much below it
+const SINGULAR_DEPTH: usize = 8;
+
+// How far below the hash move's score, per ply of depth, every other move has to be for it to be singular
+const SINGULAR_MARGIN: isize = 2;
+
+// How close to the leaves reverse futility pruning, razoring and futility pruning happen.
+// The margins they use per ply of depth are options, so they can be tuned
+const REVERSE_FUTILITY_DEPTH: usize = 6;
+const RAZORING_DEPTH: usize = 2;
+const FUTILITY_DEPTH: usize = 3;
+
+// Late move reductions start at this depth, with the move after the first LMR_MOVES moves
+const LMR_DEPTH: usize = 3;
+const LMR_MOVES: usize = 3;
+
+// A quiet move whose history is worth this many cutoffs at the current depth is reduced one ply less
+const LMR_HISTORY_CUTOFFS: isize = 8;
+
+// Where the reduction table stops, later moves are reduced as much as the last one in it
+const MAX_MOVES: usize = 64;
+
+// Late move pruning only happens this close to the leaves, and keeps LMP_MOVES + depth² moves
+const LMP_DEPTH: usize = 3;
+const LMP_MOVES: usize = 3;
+
+// Quiet moves that lose more than this per ply of depth in the exchange that follows are pruned
+const SEE_QUIET_DEPTH: usize = 3;
+const SEE_QUIET_MARGIN: isize = 60;
+
+// Slack given to a capture before delta pruning decides it can't possibly bring the score back to alpha or beta
+const DELTA_MARGIN: isize = 200;
+
+// Flags the UCI thread uses to steer a running search
+#[derive(Clone, Debug, Default)]
+pub struct Signals {
+    pub stop: Arc<AtomicBool>,
+    // Set while we're thinking on the opponent's time, cleared by ponderhit
+    pub ponder: Arc<AtomicBool>,
+}
+
+// The positions that led up to the current one, needed to see repetitions and the fifty move rule
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    // Hashes of every earlier position, oldest first
+    hashes: Vec<u64>,
+    // Plies since the last capture or pawn move
+    halfmove_clock: usize,
+}
+
+impl History {
+    pub fn new(halfmove_clock: usize) -> Self {
+        Self {
+            hashes: Vec::new(),
+            halfmove_clock,
+        }
+    }
+
+    // Records the position the move is played from
+    pub fn push(&mut self, board: &Board, chess_move: ChessMove) {
+        let irreversible = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+            || board.piece_on(chess_move.get_dest()).is_some();
+
+        self.hashes.push(board.get_hash());
+        self.halfmove_clock = if irreversible {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+    }
+}
+
+pub struct Search<'a> {
+    board: Board,
+    transposition_table: &'a mut TranspositionTable,
+    max_depth: usize,
+    max_nodes: Option<usize>,
+    // Set by go mate, the search ends as soon as a mate in this many moves is proven
+    max_mate: Option<usize>,
+    mate_found: bool,
+    // Off in mate searches, which have to look at every move to say there's no mate
+    pruning: bool,
+    // The moves the GUI asked us to look at with searchmoves, empty means all of them
+    root_moves: Vec<ChessMove>,
+    // How many of the best lines to find with MultiPV
+    multi_pv: usize,
+    // Root moves that already got a line in this iteration, so the next line has to be another move
+    excluded_root_moves: Vec<ChessMove>,
+    killers: Vec<Killers>,
+    history_table: HistoryTable,
+    pawn_table: PawnTable,
+    // Late move reductions by depth and move number
+    reductions: Vec<[usize; MAX_MOVES]>,
+    futility_margin: isize,
+    reverse_futility_margin: isize,
+    razoring_margin: isize,
+    time_manager: TimeManager,
+    // Unlike the time manager this isn't reset on ponderhit, it's what the GUI is shown
+    start: Instant,
+    last_report: Instant,
+    signals: Signals,
+    pondering: bool,
+    history: History,
+    // Where the search starts in the history, repeating anything after it is enough for a draw
+    root_history_length: usize,
+    root_depth: usize,
+    // Distance from the root of the node being searched
+    ply: usize,
+    // The deepest ply reached in this iteration, quiescence included
+    seldepth: usize,
+    nodes: usize,
+    // Cleared for the child of a null move and for verification searches, passing twice in a row proves nothing
+    null_move_allowed: bool,
+    // The hash move while a singular extension checks how good the other moves are without it
+    excluded_move: Option<ChessMove>,
+    stopped: bool,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(
+        board: Board,
+        go_options: &GoOptions,
+        options: &Options,
+        signals: Signals,
+        history: History,
+        transposition_table: &'a mut TranspositionTable,
+    ) -> Self {
+        // No mate longer than the deepest search can be proven, and a bigger one would overflow below
+        let max_mate = match go_options.mate {
+            0 => None,
+            mate => Some(mate.min(MAX_DEPTH)),
+        };
+
+        // Proving a mate in n moves takes a search of our n moves and the n - 1 replies in between
+        let max_depth = match (go_options.depth, max_mate) {
+            (0, None) => MAX_DEPTH,
+            (0, Some(mate)) => mate * 2 - 1,
+            (depth, None) => depth,
+            (depth, Some(mate)) => depth.min(mate * 2 - 1),
+        }
+        .min(MAX_DEPTH);
+
+        let max_nodes = match go_options.nodes {
+            0 => None,
+            nodes => Some(nodes),
+        };
+
+        let time_manager = TimeManager::new(go_options, options, board.side_to_move());
+        let pondering = signals.ponder.load(Ordering::Relaxed);
+
+        transposition_table.new_search();
+
+        let start = Instant::now();
+
+        Self {
+            board,
+            transposition_table,
+            max_depth,
+            max_nodes,
+            max_mate,
+            mate_found: false,
+            pruning: max_mate.is_none(),
+            root_moves: go_options.search_moves.clone(),
+            multi_pv: options.spin(MULTI_PV) as usize,
+            excluded_root_moves: Vec::new(),
+            killers: vec![Killers::default(); MAX_DEPTH],
+            history_table: HistoryTable::default(),
+            pawn_table: PawnTable::default(),
+            reductions: reduction_table(),
+            futility_margin: options.spin(FUTILITY_MARGIN) as isize,
+            reverse_futility_margin: options.spin(REVERSE_FUTILITY_MARGIN) as isize,
+            razoring_margin: options.spin(RAZORING_MARGIN) as isize,
+            time_manager,
+            start,
+            last_report: start,
+            signals,
+            pondering,
+            root_history_length: history.hashes.len(),
+            history,
+            root_depth: 0,
+            ply: 0,
+            seldepth: 0,
+            nodes: 0,
+            null_move_allowed: true,
+            excluded_move: None,
+            stopped: false,
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    // Searches depth 1, 2, 3... and returns the principal variation of the last iteration that wasn't cut short
+    pub fn iterative_deepening(&mut self) -> Result<Vec<ChessMove>, IoError> {
+        let mut principal_variation = Vec::new();
+        let mut previous_scores = Vec::new();
+
+        for depth in 1..=self.max_depth {
+            let mut lines = self.search_lines(depth, &previous_scores);
+
+            // A partial first line can't be trusted, since most root moves haven't been looked at yet
+            if lines.is_empty() {
+                break;
+            }
+
+            for (index, (score, pv)) in lines.iter().enumerate() {
+                self.report_iteration(index + 1, *score, pv)?;
+            }
+
+            previous_scores = lines.iter().map(|(score, _)| *score).collect();
+
+            let (score, pv) = lines.swap_remove(0);
+            principal_variation = pv;
+
+            self.mate_found = self
+                .max_mate
+                .is_some_and(|mate| score >= MATE - (mate * 2 - 1) as isize);
+
+            if self.stopped || self.mate_found || self.out_of_budget() {
+                break;
+            }
+        }
+
+        if let (Some(mate), true, false) = (
+            self.max_mate,
+            self.no_mate_found(),
+            principal_variation.is_empty(),
+        ) {
+            let mut stdout = stdout().lock();
+            writeln!(stdout, "info string No mate in {} found", mate)?;
+            stdout.flush()?;
+        }
+
+        Ok(principal_variation)
+    }
+
+    // Finds the MultiPV best lines of an iteration, best first. Every line is a full search of the root
+    // without the moves of the lines before it, so each score is exact. There are fewer lines when
+    // the root doesn't have enough moves, or the search was stopped
+    fn search_lines(
+        &mut self,
+        depth: usize,
+        previous_scores: &[isize],
+    ) -> Vec<(isize, Vec<ChessMove>)> {
+        self.root_depth = depth;
+        self.seldepth = 0;
+        self.excluded_root_moves.clear();
+
+        let mut lines = Vec::new();
+
+        while lines.len() < self.multi_pv {
+            let mut pv = Vec::new();
+            let previous_score = previous_scores.get(lines.len()).copied();
+            let score = self.aspiration_search(depth, previous_score, &mut pv);
+
+            if self.stopped || pv.is_empty() {
+                break;
+            }
+
+            self.excluded_root_moves.push(pv[0]);
+            lines.push((score, pv));
+        }
+
+        lines.sort_by_key(|(score, _)| -score);
+
+        lines
+    }
+
+    // Only a mate search that made it through every iteration can say there's no mate
+    fn no_mate_found(&self) -> bool {
+        self.max_mate.is_some()
+            && !self.mate_found
+            && !self.stopped
+            && self.root_depth == self.max_depth
+    }
+
+    // Searches the root in a window around the score of the last iteration, a narrow window cuts off a lot more.
+    // When the score lands outside of it, the window is widened on that side and the root searched again
+    fn aspiration_search(
+        &mut self,
+        depth: usize,
+        previous_score: Option<isize>,
+        pv: &mut Vec<ChessMove>,
+    ) -> isize {
+        let board = self.board;
+        let mut delta = ASPIRATION_WINDOW;
+
+        // Mate scores jump around too much between iterations to guess them
+        let (mut alpha, mut beta) = match previous_score {
+            Some(score) if depth >= ASPIRATION_DEPTH && score.abs() < MATE_BOUND => {
+                (score - delta, score + delta)
+            }
+            _ => (-INFINITY, INFINITY),
+        };
+
+        loop {
+            pv.clear();
+            let (score, _) = self.negamax(&board, depth, alpha, beta, pv);
+
+            if self.stopped || pv.is_empty() {
+                return score;
+            }
+
+            if score <= alpha && alpha > -INFINITY {
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta && beta < INFINITY {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+
+            delta *= 2;
+        }
+    }
+
+    fn report_iteration(
+        &self,
+        multi_pv: usize,
+        score: isize,
+        pv: &[ChessMove],
+    ) -> Result<(), IoError> {
+        // The line is written in pieces, the lock keeps the UCI thread from printing in between them
+        let mut stdout = stdout().lock();
+        let elapsed = self.start.elapsed();
+
+        write!(
+            stdout,
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} hashfull {} pv",
+            self.root_depth,
+            self.seldepth,
+            multi_pv,
+            uci_score(score),
+            self.nodes,
+            nodes_per_second(self.nodes, elapsed),
+            elapsed.as_millis(),
+            self.transposition_table.hashfull(),
+        )?;
+
+        for chess_move in pv {
+            write!(stdout, " {}", chess_move)?;
+        }
+
+        writeln!(stdout)?;
+        stdout.flush()
+    }
+
+    // Keeps the GUI from thinking we hung during long iterations.
+    // Output errors are left for the end of the iteration to deal with, the search can't do anything about them
+    fn report_progress(&mut self) {
+        let mut stdout = stdout().lock();
+        let elapsed = self.start.elapsed();
+
+        self.last_report = Instant::now();
+
+        let _ = writeln!(
+            stdout,
+            "info depth {} seldepth {} nodes {} nps {} time {} hashfull {}",
+            self.root_depth,
+            self.seldepth,
+            self.nodes,
+            nodes_per_second(self.nodes, elapsed),
+            elapsed.as_millis(),
+            self.transposition_table.hashfull(),
+        )
+        .and_then(|_| stdout.flush());
+    }
+
+    fn report_current_move(&self, chess_move: ChessMove, move_number: usize) {
+        let mut stdout = stdout().lock();
+
+        let _ = writeln!(
+            stdout,
+            "info depth {} currmove {} currmovenumber {}",
+            self.root_depth, chess_move, move_number
+        )
+        .and_then(|_| stdout.flush());
+    }
+
+    // Only aborts once the first iteration is done, so there's always a move to play
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        let checkup = self.nodes.is_multiple_of(CHECKUP_INTERVAL);
+
+        if checkup && self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.report_progress();
+        }
+
+        if self.root_depth > 1 {
+            let limit_reached = self.max_nodes.is_some_and(|max| self.nodes >= max);
+
+            self.stopped = limit_reached
+                || (checkup
+                    && (self.signals.stop.load(Ordering::Relaxed)
+                        || self.on_the_clock() && self.time_manager.hard_limit_reached()));
+        }
+
+        self.stopped
+    }
+
+    // Checked between iterations, the next one would most likely not finish anyway
+    fn out_of_budget(&mut self) -> bool {
+        self.signals.stop.load(Ordering::Relaxed)
+            || self.max_nodes.is_some_and(|max| self.nodes >= max)
+            || self.on_the_clock() && self.time_manager.soft_limit_reached()
+    }
+
+    // The clock only applies once the opponent played the move we pondered on, and it starts at the ponderhit
+    fn on_the_clock(&mut self) -> bool {
+        if self.pondering && !self.signals.ponder.load(Ordering::Relaxed) {
+            self.pondering = false;
+            self.time_manager.restart();
+        }
+
+        !self.pondering
+    }
+
+    // Scores are always from the point of view of the side to move, a child's score is negated for its parent
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        mut alpha: isize,
+        mut beta: isize,
+        pv: &mut Vec<ChessMove>,
+    ) -> (isize, Option<ChessMove>) {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(self.ply);
+
+        let null_move_allowed = mem::replace(&mut self.null_move_allowed, true);
+        let excluded_move = self.excluded_move.take();
+
+        if self.should_stop() {
+            return (0, None);
+        }
+
+        if depth == 0 {
+            return (self.quiescence(board, alpha, beta), None);
+        }
+
+        if self.ply > 0 {
+            // Even mating right away can't beat a mate we already found closer to the root
+            alpha = alpha.max(-(MATE - self.ply as isize));
+            beta = beta.min(MATE - self.ply as isize - 1);
+
+            if alpha >= beta {
+                return (alpha, None);
+            }
+        }
+
+        let hash = board.get_hash();
+
+        if self.ply > 0 && self.is_draw(hash) {
+            return (0, None);
+        }
+
+        let entry = self.transposition_table.probe(hash);
+
+        // A zero window means the node only has to prove a bound, principal variation nodes are searched in full
+        let pv_node = beta - alpha > 1;
+
+        // A cutoff would leave the principal variation ending at this node, so the root and the other
+        // principal variation nodes are always searched. The entry of a singular search is about
+        // the position with every move in it
+        if let (Some(entry), false, None) = (entry, pv_node || self.ply == 0, excluded_move) {
+            if entry.depth as usize >= depth {
+                let score = score_from_table(entry.score as isize, self.ply);
+
+                match entry.bound {
+                    Bound::Exact => return (score, entry.best_move),
+                    Bound::Lower if score >= beta => return (score, entry.best_move),
+                    Bound::Upper if score <= alpha => return (score, entry.best_move),
+                    _ => (),
+                }
+            }
+        }
+
+        let in_check = *board.checkers() != EMPTY;
+
+        // Pruning on the static evaluation only happens in zero window nodes, and not in check,
+        // where the evaluation says nothing about how the position will turn out
+        let static_eval =
+            (self.pruning && !pv_node && !in_check && self.ply > 0 && excluded_move.is_none())
+                .then(|| evaluate(board, &mut self.pawn_table));
+
+        if let Some(static_eval) = static_eval {
+            if let Some(score) = self.frontier_cutoff(board, depth, alpha, beta, static_eval) {
+                return (score, None);
+            }
+
+            if null_move_allowed {
+                if let Some(score) = self.null_move_cutoff(board, depth, beta, static_eval) {
+                    return (score, None);
+                }
+            }
+        }
+
+        // When even a good quiet move can't bring the static evaluation up to alpha, only the
+        // captures, promotions and checks are searched. Never against a mate score, no margin gets there
+        let futile = static_eval.is_some_and(|static_eval| {
+            depth <= FUTILITY_DEPTH
+                && alpha.abs() < MATE_BOUND
+                && static_eval + self.futility_margin * depth as isize <= alpha
+        });
+
+        let singular_move = match excluded_move {
+            None => self.singular_move(board, depth, entry),
+            Some(_) => None,
+        };
+
+        let hash_move = entry.and_then(|entry| entry.best_move);
+        let killers = self.killers.get(self.ply).copied().unwrap_or_default();
+        let mut move_picker = MovePicker::new(board, hash_move, killers);
+
+        if !move_picker.has_legal_moves() {
+            return (self.terminal_score(board), None);
+        }
+
+        let alpha_original = alpha;
+        let mut child_pv = Vec::new();
+        let mut best_eval = -INFINITY;
+        let mut move_result = None;
+        let mut move_number = 0;
+
+        while let Some(chess_move) = move_picker.next(board, &self.history_table) {
+            if self.ply == 0 && !self.is_root_move(chess_move) || Some(chess_move) == excluded_move
+            {
+                continue;
+            }
+
+            move_number += 1;
+
+            if self.ply == 0 && self.start.elapsed() >= REPORT_INTERVAL {
+                self.report_current_move(chess_move, move_number);
+            }
+
+            let new_board = board.make_move_new(chess_move);
+
+            if static_eval.is_some()
+                && self.quiet_move_pruned(board, &new_board, chess_move, depth, move_number, futile)
+            {
+                continue;
+            }
+
+            let child_depth = depth - 1 + self.extension(&new_board, chess_move, singular_move);
+
+            child_pv.clear();
+            let halfmove_clock = self.enter(board, chess_move);
+
+            // With good move ordering the first move is the best one, so the rest only get a zero window
+            // to prove they're worse, and a full search if it turns out they might not be
+            let eval = if move_number == 1 {
+                -self
+                    .negamax(&new_board, child_depth, -beta, -alpha, &mut child_pv)
+                    .0
+            } else {
+                let reduction =
+                    self.reduction(board, &new_board, chess_move, depth, move_number, pv_node);
+                let mut eval = -self
+                    .negamax(
+                        &new_board,
+                        child_depth - reduction,
+                        -alpha - 1,
+                        -alpha,
+                        &mut child_pv,
+                    )
+                    .0;
+
+                // A reduced move that beats alpha gets its full depth back before it's trusted
+                if reduction > 0 && eval > alpha && !self.stopped {
+                    child_pv.clear();
+                    eval = -self
+                        .negamax(&new_board, child_depth, -alpha - 1, -alpha, &mut child_pv)
+                        .0;
+                }
+
+                if eval > alpha && eval < beta && !self.stopped {
+                    child_pv.clear();
+                    eval = -self
+                        .negamax(&new_board, child_depth, -beta, -alpha, &mut child_pv)
+                        .0;
+                }
+
+                eval
+            };
+
+            self.leave(halfmove_clock);
+
+            if self.stopped {
+                return (0, None);
+            }
+
+            if best_eval < eval {
+                best_eval = eval;
+                move_result = Some(chess_move);
+
+                pv.clear();
+                pv.push(chess_move);
+                pv.extend_from_slice(&child_pv);
+            }
+
+            alpha = alpha.max(eval);
+
+            if beta <= alpha {
+                self.reward_cutoff(board, chess_move, depth);
+                break;
+            }
+        }
+
+        // Only a root without any moves left for searchmoves or MultiPV,
+        // or a singular search of a position with just the hash move, ends up here
+        if move_result.is_none() {
+            return (-INFINITY, None);
+        }
+
+        let bound = if best_eval >= beta {
+            Bound::Lower
+        } else if best_eval <= alpha_original {
+            Bound::Upper
+        } else {
+            Bound::Exact
+        };
+
+        if excluded_move.is_none() {
+            self.store(hash, depth, bound, best_eval, move_result);
+        }
+
+        (best_eval, move_result)
+    }
+
+    // Only searches captures and promotions, so the leaves never get evaluated in the middle of an exchange
+    fn quiescence(&mut self, board: &Board, mut alpha: isize, beta: isize) -> isize {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(self.ply);
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        let in_check = *board.checkers() != EMPTY;
+        let mut move_gen = MoveGen::new_legal(board);
+
+        // In check every evasion has to be looked at, and standing pat isn't an option
+        let stand_pat = if in_check {
+            None
+        } else {
+            move_gen.set_iterator_mask(tactical_targets(board));
+            Some(evaluate(board, &mut self.pawn_table))
+        };
+
+        let mut moves: Vec<ChessMove> = move_gen
+            .filter(|&chess_move| in_check || is_tactical(board, chess_move))
+            .collect();
+
+        // Every legal move was generated when in check, so no moves means we're mated
+        if in_check && moves.is_empty() {
+            return self.terminal_score(board);
+        }
+
+        moves.sort_by_key(|&chess_move| -mvv_lva(board, chess_move));
+
+        let mut best_eval = -INFINITY;
+
+        if let Some(stand_pat) = stand_pat {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+
+            best_eval = stand_pat;
+            alpha = alpha.max(stand_pat);
+        }
+
+        for chess_move in moves {
+            // Out of check, captures that can't get back to alpha or lose material in the exchange are skipped
+            if let Some(stand_pat) = stand_pat {
+                if stand_pat + material_gain(board, chess_move) + DELTA_MARGIN < alpha
+                    || see(board, chess_move) < 0
+                {
+                    continue;
+                }
+            }
+
+            let new_board = board.make_move_new(chess_move);
+
+            self.ply += 1;
+            let eval = -self.quiescence(&new_board, -beta, -alpha);
+            self.ply -= 1;
+
+            if self.stopped {
+                return 0;
+            }
+
+            best_eval = best_eval.max(eval);
+            alpha = alpha.max(eval);
+
+            if beta <= alpha {
+                break;
+            }
+        }
+
+        best_eval
+    }
+
+    // Frontier nodes whose static evaluation is far enough on the wrong side of the window end right away.
+    // Reverse futility trusts an evaluation that beats beta by a margin per ply of depth left,
+    // razoring drops an evaluation far below alpha into the quiescence search to see if a capture saves it.
+    // The quiescence search can't see a mate though, so positions with a check in them aren't razored
+    fn frontier_cutoff(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        alpha: isize,
+        beta: isize,
+        static_eval: isize,
+    ) -> Option<isize> {
+        // Mates need a real search to prove them
+        if alpha.abs() >= MATE_BOUND || beta.abs() >= MATE_BOUND {
+            return None;
+        }
+
+        let reverse_futility_margin = self.reverse_futility_margin * depth as isize;
+        let razoring_margin = self.razoring_margin * depth as isize;
+
+        if depth <= REVERSE_FUTILITY_DEPTH && static_eval - reverse_futility_margin >= beta {
+            return Some(static_eval - reverse_futility_margin);
+        }
+
+        if depth <= RAZORING_DEPTH && static_eval + razoring_margin <= alpha && !has_check(board) {
+            let score = self.quiescence(board, alpha, alpha + 1);
+
+            return (score <= alpha).then_some(score);
+        }
+
+        None
+    }
+
+    // Passing the turn is almost always worse than the best move, so when giving the opponent a free move
+    // still beats beta in a shallower search, the real search most likely would too.
+    // That's wrong in zugzwang, so it's left out of pawn endgames, and deep cutoffs are verified
+    fn null_move_cutoff(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        beta: isize,
+        static_eval: isize,
+    ) -> Option<isize> {
+        if depth < NULL_MOVE_DEPTH || !has_non_pawn_material(board) || static_eval < beta {
+            return None;
+        }
+
+        // There's no passing while in check
+        let new_board = board.null_move()?;
+        let reduced_depth = depth.saturating_sub(NULL_MOVE_REDUCTION + depth / 6 + 1);
+
+        let halfmove_clock = self.enter_null(board);
+        self.null_move_allowed = false;
+        let score = -self
+            .negamax(&new_board, reduced_depth, -beta, -beta + 1, &mut Vec::new())
+            .0;
+        self.leave(halfmove_clock);
+
+        if self.stopped || score < beta {
+            return None;
+        }
+
+        if depth >= VERIFICATION_DEPTH {
+            self.null_move_allowed = false;
+            let score = self
+                .negamax(board, reduced_depth, beta - 1, beta, &mut Vec::new())
+                .0;
+
+            if self.stopped || score < beta {
+                return None;
+            }
+        }
+
+        // Getting mated after passing doesn't make it a forced mate, so only the bound is trusted
+        if score >= MATE_BOUND {
+            Some(beta)
+        } else {
+            Some(score)
+        }
+    }
+
+    // Checks and hash moves that are the only good move get a ply more, so forcing lines are seen to the end.
+    // Lines twice as long as the iteration aren't extended any further, or the tree could blow up
+    fn extension(
+        &self,
+        new_board: &Board,
+        chess_move: ChessMove,
+        singular_move: Option<ChessMove>,
+    ) -> usize {
+        let forcing = *new_board.checkers() != EMPTY || singular_move == Some(chess_move);
+
+        usize::from(forcing && self.ply < 2 * self.root_depth)
+    }
+
+    // The hash move is singular when a shallower search without it finds every other move well below
+    // the score the table has for it. Only a hash entry from a deep enough search that didn't fail low counts
+    fn singular_move(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        entry: Option<Entry>,
+    ) -> Option<ChessMove> {
+        let entry = entry?;
+        let hash_move = entry
+            .best_move
+            .filter(|&chess_move| board.legal(chess_move))?;
+
+        if depth < SINGULAR_DEPTH || self.ply == 0 || entry.depth as usize + 3 < depth {
+            return None;
+        }
+
+        let score = score_from_table(entry.score as isize, self.ply);
+
+        if score.abs() >= MATE_BOUND || entry.bound == Bound::Upper {
+            return None;
+        }
+
+        let singular_beta = score - SINGULAR_MARGIN * depth as isize;
+
+        self.excluded_move = Some(hash_move);
+        self.null_move_allowed = false;
+        let eval = self
+            .negamax(
+                board,
+                (depth - 1) / 2,
+                singular_beta - 1,
+                singular_beta,
+                &mut Vec::new(),
+            )
+            .0;
+
+        (eval < singular_beta && !self.stopped).then_some(hash_move)
+    }
+
+    // How many plies shallower a late quiet move is searched, after the first few moves of a well ordered
+    // list they're rarely the best. Moves that often caused cutoffs before are reduced less, as is the principal
+    // variation, where a mistake costs the most. Checks are extended and evasions are too few to order well,
+    // so neither is ever reduced
+    fn reduction(
+        &self,
+        board: &Board,
+        new_board: &Board,
+        chess_move: ChessMove,
+        depth: usize,
+        move_number: usize,
+        pv_node: bool,
+    ) -> usize {
+        if !self.pruning
+            || depth < LMR_DEPTH
+            || move_number <= LMR_MOVES
+            || !is_quiet(board, chess_move)
+            || *board.checkers() != EMPTY
+            || *new_board.checkers() != EMPTY
+        {
+            return 0;
+        }
+
+        let mut reduction =
+            self.reductions[depth.min(MAX_DEPTH)][move_number.min(MAX_MOVES - 1)] as isize;
+
+        if pv_node {
+            reduction -= 1;
+        }
+
+        let history = self.history_table.get(board, chess_move);
+
+        if history == 0 {
+            reduction += 1;
+        } else if history >= (depth * depth) as isize * LMR_HISTORY_CUTOFFS {
+            reduction -= 1;
+        }
+
+        // The reduced search always gets at least one ply before the quiescence search
+        reduction.clamp(0, depth as isize - 2) as usize
+    }
+
+    // Close to the leaves the quiet moves at the end of the list are skipped altogether, as are the ones that
+    // hang material and all quiet moves after the first one when the node is futile.
+    // Never checks, where the one move that matters could be anywhere
+    fn quiet_move_pruned(
+        &self,
+        board: &Board,
+        new_board: &Board,
+        chess_move: ChessMove,
+        depth: usize,
+        move_number: usize,
+        futile: bool,
+    ) -> bool {
+        if move_number == 1 || *new_board.checkers() != EMPTY || !is_quiet(board, chess_move) {
+            return false;
+        }
+
+        let late = depth <= LMP_DEPTH && move_number > LMP_MOVES + depth * depth;
+        let hangs_material = || {
+            depth <= SEE_QUIET_DEPTH && see(board, chess_move) < -SEE_QUIET_MARGIN * depth as isize
+        };
+
+        late || futile || hangs_material()
+    }
+
+    fn is_root_move(&self, chess_move: ChessMove) -> bool {
+        (self.root_moves.is_empty() || self.root_moves.contains(&chess_move))
+            && !self.excluded_root_moves.contains(&chess_move)
+    }
+
+    // Quiet moves that cause a cutoff get tried early in the rest of the search
+    fn reward_cutoff(&mut self, board: &Board, chess_move: ChessMove, depth: usize) {
+        if !is_quiet(board, chess_move) {
+            return;
+        }
+
+        self.history_table.reward(board, chess_move, depth);
+
+        if let Some(killers) = self.killers.get_mut(self.ply) {
+            if killers[0] != Some(chess_move) {
+                killers[1] = killers[0];
+                killers[0] = Some(chess_move);
+            }
+        }
+    }
+
+    // Steps into a child node, keeping the ply and the history up to date.
+    // The quiescence search doesn't bother, since captures and promotions can't repeat a position
+    fn enter(&mut self, board: &Board, chess_move: ChessMove) -> usize {
+        let halfmove_clock = self.history.halfmove_clock;
+
+        self.ply += 1;
+        self.history.push(board, chess_move);
+
+        halfmove_clock
+    }
+
+    // Nothing played before a null move can be repeated through it, so the halfmove clock starts over
+    fn enter_null(&mut self, board: &Board) -> usize {
+        let halfmove_clock = self.history.halfmove_clock;
+
+        self.ply += 1;
+        self.history.hashes.push(board.get_hash());
+        self.history.halfmove_clock = 0;
+
+        halfmove_clock
+    }
+
+    fn leave(&mut self, halfmove_clock: usize) {
+        self.ply -= 1;
+        self.history.hashes.pop();
+        self.history.halfmove_clock = halfmove_clock;
+    }
+
+    // A repetition inside the search is scored as a draw right away, since whoever could avoid it didn't.
+    // Positions from before the root have to be on the board twice already to make it a threefold
+    fn is_draw(&self, hash: u64) -> bool {
+        if self.history.halfmove_clock >= 100 {
+            return true;
+        }
+
+        let hashes = &self.history.hashes;
+        let mut repetitions = 0;
+
+        // Only positions with the same side to move, played after the last irreversible move, can be repeats
+        for index in (0..hashes.len())
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .take(self.history.halfmove_clock / 2)
+        {
+            if hashes[index] == hash {
+                if index >= self.root_history_length {
+                    return true;
+                }
+
+                repetitions += 1;
+
+                if repetitions >= 2 {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    // The score of a position without legal moves, for the side that has to move in it
+    fn terminal_score(&self, board: &Board) -> isize {
+        if *board.checkers() == EMPTY {
+            return 0;
+        }
+
+        -(MATE - self.ply as isize)
+    }
+
+    // A root restricted by searchmoves or MultiPV isn't stored, its score only holds for some of the moves
+    fn store(
+        &mut self,
+        hash: u64,
+        depth: usize,
+        bound: Bound,
+        score: isize,
+        best_move: Option<ChessMove>,
+    ) {
+        if self.ply == 0 && !(self.root_moves.is_empty() && self.excluded_root_moves.is_empty()) {
+            return;
+        }
+
+        self.transposition_table.store(
+            hash,
+            depth,
+            bound,
+            score_to_table(score, self.ply),
+            best_move,
+        );
+    }
+}
+
+// Mates are reported in moves rather than plies, negative when we're the ones getting mated
+fn uci_score(score: isize) -> String {
+    if score >= MATE_BOUND {
+        format!("mate {}", (MATE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        format!("mate -{}", (MATE + score + 1) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+pub fn nodes_per_second(nodes: usize, elapsed: Duration) -> u128 {
+    nodes as u128 * 1000 / elapsed.as_millis().max(1)
+}
+
+// Mate scores are counted from the root, but the table needs them counted from the position itself,
+// since the same position can be reached at another ply
+fn score_to_table(score: isize, ply: usize) -> isize {
+    if score >= MATE_BOUND {
+        score + ply as isize
+    } else if score <= -MATE_BOUND {
+        score - ply as isize
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: isize, ply: usize) -> isize {
+    if score >= MATE_BOUND {
+        score - ply as isize
+    } else if score <= -MATE_BOUND {
+        score + ply as isize
+    } else {
+        score
+    }
+}
+
+// The deeper the node and the later the move, the less likely it is to matter and the more there is to save
+fn reduction_table() -> Vec<[usize; MAX_MOVES]> {
+    (0..=MAX_DEPTH)
+        .map(|depth| {
+            std::array::from_fn(|move_number| match (depth, move_number) {
+                (0, _) | (_, 0) => 0,
+                _ => (0.75 + (depth as f64).ln() * (move_number as f64).ln() / 2.25) as usize,
+            })
+        })
+        .collect()
+}
+
+// Whether a piece of the side to move reaches a square it would give check from. Much cheaper than
+// making every move, it misses discovered checks and checks by promotion, rare enough close to the leaves
+fn has_check(board: &Board) -> bool {
+    let color = board.side_to_move();
+    let king = board.king_square(!color);
+    let occupied = *board.combined();
+    let open = !board.color_combined(color);
+
+    let diagonal = get_bishop_moves(king, occupied) & open;
+    let straight = get_rook_moves(king, occupied) & open;
+    let checking_squares = [
+        (Piece::Knight, get_knight_moves(king) & open),
+        (Piece::Bishop, diagonal),
+        (Piece::Rook, straight),
+        (Piece::Queen, diagonal | straight),
+    ];
+
+    let piece_checks = checking_squares.into_iter().any(|(piece, squares)| {
+        let pieces = board.pieces(piece) & board.color_combined(color);
+
+        pieces
+            .into_iter()
+            .any(|square| attacks(board, piece, square) & squares != EMPTY)
+    });
+
+    // Pawns only check by pushing onto an empty square next to the king diagonally
+    let pawn_squares = get_pawn_attacks(king, !color, !EMPTY) & !occupied;
+    let pawns = board.pieces(Piece::Pawn) & board.color_combined(color);
+    let pawn_checks = pawns.into_iter().any(|square| {
+        square
+            .forward(color)
+            .is_some_and(|stop| pawn_squares & BitBoard::from_square(stop) != EMPTY)
+    });
+
+    piece_checks || pawn_checks
+}
+
+// Pawn endgames are where zugzwang happens, and passing the turn there can look better than every move
+fn has_non_pawn_material(board: &Board) -> bool {
+    let pawns_and_king = board.pieces(Piece::Pawn) | board.pieces(Piece::King);
+
+    board.color_combined(board.side_to_move()) & !pawns_and_king != EMPTY
+}
+
+// Enemy pieces and the promotion rank, where every capture and promotion lands.
+// En passant captures are left out, they're rarely what decides an exchange
+fn tactical_targets(board: &Board) -> BitBoard {
+    let promotion_rank = get_rank(board.side_to_move().to_their_backrank());
+
+    board.color_combined(!board.side_to_move()) | promotion_rank
+}
+
+// Under promotions are never worth it in the quiescence search
+fn is_tactical(board: &Board, chess_move: ChessMove) -> bool {
+    match chess_move.get_promotion() {
+        Some(piece) => piece == Piece::Queen,
+        None => board.piece_on(chess_move.get_dest()).is_some(),
+    }
+}
+
+// The most material a move can win, before the opponent gets to recapture
+fn material_gain(board: &Board, chess_move: ChessMove) -> isize {
+    let captured = board
+        .piece_on(chess_move.get_dest())
+        .map_or(0, piece_to_score);
+    let promoted = chess_move.get_promotion().map_or(0, |piece| {
+        piece_to_score(piece) - piece_to_score(Piece::Pawn)
+    });
+
+    captured + promoted
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    // A search of the position with nothing played before it
+    fn search_for<'a>(
+        board: Board,
+        go_options: &GoOptions,
+        options: &Options,
+        transposition_table: &'a mut TranspositionTable,
+    ) -> Search<'a> {
+        Search::new(
+            board,
+            go_options,
+            options,
+            Signals::default(),
+            History::default(),
+            transposition_table,
+        )
+    }
+
+    fn best_move(fen: &str, depth: usize) -> ChessMove {
+        principal_variation(fen, depth)[0]
+    }
+
+    fn principal_variation(fen: &str, depth: usize) -> Vec<ChessMove> {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let go_options = GoOptions {
+            depth,
+            ..Default::default()
+        };
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &go_options,
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        search.iterative_deepening().expect("Writing to stdout")
+    }
+
+    fn chess_move(notation: &str) -> ChessMove {
+        ChessMove::from_str(notation).expect("Valid move")
+    }
+
+    #[test]
+    fn queen_doesnt_take_a_defended_pawn() {
+        let chess_move = best_move("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", 1);
+
+        assert_ne!(chess_move, self::chess_move("d1d5"));
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let chess_move = best_move("3qk3/8/8/8/8/8/8/3RK3 w - - 0 1", 2);
+
+        assert_eq!(chess_move, self::chess_move("d1d8"));
+    }
+
+    #[test]
+    fn sees_the_whole_exchange_on_a_square() {
+        // Rxe5 wins a pawn on the surface, but the knight on c6 takes back and the rook is lost for it
+        let chess_move = best_move("4k3/8/2n5/4p3/8/8/8/4RK2 w - - 0 1", 1);
+
+        assert_ne!(chess_move, self::chess_move("e1e5"));
+    }
+
+    #[test]
+    fn threefold_repetition_before_the_root_is_a_draw() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let mut board = Board::default();
+        let mut history = History::default();
+        let mut transposition_table = TranspositionTable::new(1);
+
+        for (ply, notation) in shuffle.iter().cycle().take(8).enumerate() {
+            let chess_move = chess_move(notation);
+            history.push(&board, chess_move);
+            board = board.make_move_new(chess_move);
+
+            let search = Search::new(
+                board,
+                &GoOptions::default(),
+                &Options::default(),
+                Signals::default(),
+                history.clone(),
+                &mut transposition_table,
+            );
+
+            // The starting position is back on the board for the second time after four plies, and the third after eight
+            assert_eq!(search.is_draw(board.get_hash()), ply == 7);
+        }
+    }
+
+    #[test]
+    fn only_searches_the_moves_from_searchmoves() {
+        let board = Board::from_str("3qk3/8/8/8/8/8/8/3RK3 w - - 0 1").expect("Valid FEN");
+        let go_options = GoOptions {
+            search_moves: vec![chess_move("e1e2"), chess_move("d1d2")],
+            depth: 3,
+            ..Default::default()
+        };
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &go_options,
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        let principal_variation = search.iterative_deepening().expect("Writing to stdout");
+
+        // Rxd8 would be played otherwise
+        assert!(go_options.search_moves.contains(&principal_variation[0]));
+        assert!(principal_variation.len() > 1);
+    }
+
+    fn multi_pv_lines(fen: &str, multi_pv: usize, depth: usize) -> Vec<(isize, Vec<ChessMove>)> {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let mut options = Options::default();
+        options
+            .set(MULTI_PV, Some(&multi_pv.to_string()))
+            .expect("Valid MultiPV");
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &GoOptions::default(),
+            &options,
+            &mut transposition_table,
+        );
+
+        let mut previous_scores = Vec::new();
+        let mut lines = Vec::new();
+
+        for depth in 1..=depth {
+            lines = search.search_lines(depth, &previous_scores);
+            previous_scores = lines.iter().map(|(score, _)| *score).collect();
+        }
+
+        lines
+    }
+
+    // The score the move gets when it's the only one searched at the root
+    fn root_move_score(fen: &str, chess_move: ChessMove, depth: usize) -> isize {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let go_options = GoOptions {
+            search_moves: vec![chess_move],
+            ..Default::default()
+        };
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &go_options,
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        search.root_depth = depth;
+        search
+            .negamax(&board, depth, -INFINITY, INFINITY, &mut Vec::new())
+            .0
+    }
+
+    #[test]
+    fn multi_pv_finds_distinct_lines_best_first() {
+        let fen = "3qk3/8/8/8/8/8/4P3/3RK3 w - - 0 1";
+        let lines = multi_pv_lines(fen, 3, 3);
+        let first_moves: Vec<ChessMove> = lines.iter().map(|(_, pv)| pv[0]).collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(first_moves[0], chess_move("d1d8"));
+        assert!(first_moves[1] != first_moves[0] && first_moves[2] != first_moves[0]);
+        assert_ne!(first_moves[1], first_moves[2]);
+        assert!(lines.windows(2).all(|pair| pair[0].0 >= pair[1].0));
+
+        // Every line is scored as if it was the only move, not just as a bound below the best line
+        for (score, pv) in &lines {
+            assert_eq!(*score, root_move_score(fen, pv[0], 3), "{}", pv[0]);
+        }
+    }
+
+    #[test]
+    fn multi_pv_stops_at_the_number_of_legal_moves() {
+        // The king in the corner only has three moves
+        let lines = multi_pv_lines("7k/8/8/8/8/8/8/K7 w - - 0 1", 5, 2);
+        let mut first_moves: Vec<ChessMove> = lines.iter().map(|(_, pv)| pv[0]).collect();
+        first_moves.sort_by_key(|chess_move| chess_move.to_string());
+
+        assert_eq!(first_moves, ["a1a2", "a1b1", "a1b2"].map(chess_move));
+    }
+
+    // The score and best move of a single aspiration search, guessing the given score from a last iteration
+    fn aspiration_result(
+        fen: &str,
+        previous_score: Option<isize>,
+        depth: usize,
+    ) -> (isize, ChessMove) {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &GoOptions::default(),
+            &Options::default(),
+            &mut transposition_table,
+        );
+        let mut pv = Vec::new();
+
+        search.root_depth = depth;
+        let score = search.aspiration_search(depth, previous_score, &mut pv);
+
+        (score, pv[0])
+    }
+
+    #[test]
+    fn a_widened_aspiration_window_finds_the_full_window_result() {
+        let fen = "3qk3/8/8/8/8/8/4P3/3RK3 w - - 0 1";
+        let full_window = aspiration_result(fen, None, ASPIRATION_DEPTH);
+
+        // Guesses far above and far below the real score make the first search fail low and fail high
+        assert_eq!(
+            aspiration_result(fen, Some(2000), ASPIRATION_DEPTH),
+            full_window
+        );
+        assert_eq!(
+            aspiration_result(fen, Some(-2000), ASPIRATION_DEPTH),
+            full_window
+        );
+        assert_eq!(full_window.1, chess_move("d1d8"));
+    }
+
+    // What null_move_cutoff gives for a fresh search of the position, and the nodes it took
+    fn null_move_result(
+        fen: &str,
+        depth: usize,
+        beta: isize,
+        static_eval: isize,
+    ) -> (Option<isize>, usize) {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &GoOptions::default(),
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        search.root_depth = depth;
+        let cutoff = search.null_move_cutoff(&board, depth, beta, static_eval);
+
+        (cutoff, search.nodes())
+    }
+
+    #[test]
+    fn no_null_move_in_check_or_with_only_pawns() {
+        // A whole rook up and far above beta, but in check there's no passing
+        let in_check = null_move_result("4k3/8/8/8/8/8/4r3/R3K3 w - - 0 1", 6, -1000, 0);
+        // Passing in a pawn ending misses every zugzwang
+        let pawns_only = null_move_result("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1", 6, -1000, 0);
+
+        assert_eq!(in_check, (None, 0));
+        assert_eq!(pawns_only, (None, 0));
+    }
+
+    #[test]
+    fn verification_stops_a_null_move_cutoff_in_zugzwang() {
+        // After Kh6 every black move loses material, but passing would hold everything
+        let fen = "1q1k4/2Rr4/7K/2Q5/8/8/8/8 b - - 1 1";
+        let beta = -200;
+
+        let best_move = best_move(fen, VERIFICATION_DEPTH);
+        let score = root_move_score(fen, best_move, VERIFICATION_DEPTH);
+        // Too shallow to be verified, so the pass alone decides
+        let (unverified, _) = null_move_result(fen, VERIFICATION_DEPTH - 2, beta, 0);
+        let (verified, _) = null_move_result(fen, VERIFICATION_DEPTH, beta, 0);
+
+        assert!(score < beta);
+        assert!(unverified.is_some());
+        assert_eq!(verified, None);
+    }
+
+    // How many plies a move is reduced at the given depth and place in the move list, outside of the PV
+    fn reduction_of(fen: &str, notation: &str, depth: usize, move_number: usize) -> usize {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let chess_move = chess_move(notation);
+        let mut transposition_table = TranspositionTable::new(1);
+        let search = search_for(
+            board,
+            &GoOptions::default(),
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        let new_board = board.make_move_new(chess_move);
+        search.reduction(&board, &new_board, chess_move, depth, move_number, false)
+    }
+
+    #[test]
+    fn reductions_grow_with_the_depth_and_the_move_number() {
+        let reductions = reduction_table();
+
+        assert_eq!(reductions[0], [0; MAX_MOVES]);
+
+        for depth in 1..=MAX_DEPTH {
+            assert_eq!(reductions[depth][0], 0);
+
+            for move_number in 1..MAX_MOVES {
+                assert!(reductions[depth][move_number] >= reductions[depth][move_number - 1]);
+                assert!(reductions[depth][move_number] >= reductions[depth - 1][move_number]);
+            }
+        }
+    }
+
+    #[test]
+    fn only_late_quiet_moves_are_reduced() {
+        let fen = "4k3/8/8/3p4/8/2N5/8/R3K3 w - - 0 1";
+        let in_check = "4k3/8/8/8/8/2N5/8/R3K2r w - - 0 1";
+
+        for move_number in 1..=LMR_MOVES {
+            assert_eq!(reduction_of(fen, "a1a2", 10, move_number), 0);
+        }
+
+        assert!(reduction_of(fen, "a1a2", 10, 20) > 0);
+        assert_eq!(reduction_of(fen, "c3d5", 10, 20), 0);
+        assert_eq!(reduction_of(fen, "a1a8", 10, 20), 0);
+        assert_eq!(reduction_of(in_check, "e1e2", 10, 20), 0);
+    }
+
+    #[test]
+    fn the_first_move_is_never_pruned() {
+        let board = Board::from_str("4k3/8/8/8/8/2N5/8/R3K3 w - - 0 1").expect("Valid FEN");
+        let chess_move = chess_move("a1a2");
+        let new_board = board.make_move_new(chess_move);
+        let mut transposition_table = TranspositionTable::new(1);
+        let search = search_for(
+            board,
+            &GoOptions::default(),
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        // Even in a futile node at the leaves there's no move before it that the node could fall back on
+        assert!(!search.quiet_move_pruned(&board, &new_board, chess_move, 1, 1, true));
+        assert!(search.quiet_move_pruned(&board, &new_board, chess_move, 1, 2, true));
+    }
+
+    // What singular_move makes of a hash entry for Qxd5 with the given score, one ply below the root
+    fn singular_move_for(
+        depth: usize,
+        entry_depth: usize,
+        bound: Bound,
+        score: isize,
+    ) -> Option<ChessMove> {
+        // Every other move leaves the queen hanging or just trades it off
+        let board = Board::from_str("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").expect("Valid FEN");
+        let mut transposition_table = TranspositionTable::new(1);
+        transposition_table.store(
+            board.get_hash(),
+            entry_depth,
+            bound,
+            score,
+            Some(chess_move("d1d5")),
+        );
+        let entry = transposition_table.probe(board.get_hash());
+
+        let mut search = search_for(
+            board,
+            &GoOptions::default(),
+            &Options::default(),
+            &mut transposition_table,
+        );
+        search.root_depth = depth;
+        search.ply = 1;
+
+        search.singular_move(&board, depth, entry)
+    }
+
+    #[test]
+    fn only_a_hash_move_far_better_than_the_rest_is_singular() {
+        let depth = SINGULAR_DEPTH;
+
+        assert_eq!(
+            singular_move_for(depth, depth, Bound::Lower, 900),
+            Some(chess_move("d1d5"))
+        );
+        // The other moves hold the balance, so a hash score of zero doesn't stand out
+        assert_eq!(singular_move_for(depth, depth, Bound::Lower, 0), None);
+    }
+
+    #[test]
+    fn a_fail_low_or_a_shallow_entry_is_never_singular() {
+        let depth = SINGULAR_DEPTH;
+
+        assert_eq!(singular_move_for(depth, depth, Bound::Upper, 900), None);
+        assert_eq!(singular_move_for(depth, depth - 4, Bound::Lower, 900), None);
+        assert_eq!(singular_move_for(depth - 1, depth, Bound::Lower, 900), None);
+    }
+
+    #[test]
+    fn mate_search_stops_once_the_mate_is_proven() {
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").expect("Valid FEN");
+        let go_options = GoOptions {
+            mate: 2,
+            ..Default::default()
+        };
+        let mut transposition_table = TranspositionTable::new(1);
+        let mut search = search_for(
+            board,
+            &go_options,
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        let principal_variation = search.iterative_deepening().expect("Writing to stdout");
+
+        assert_eq!(principal_variation, vec![chess_move("a1a8")]);
+        assert_eq!(search.root_depth, 1);
+    }
+
+    #[test]
+    fn mate_search_reports_when_there_is_no_mate() {
+        // Kf7 Kh7 Rh1# is a mate in two, but there's no mate in one
+        let board = Board::from_str("7k/8/5K2/8/8/8/8/6R1 w - - 0 1").expect("Valid FEN");
+        let mut transposition_table = TranspositionTable::new(1);
+
+        for (mate, found) in [(1, false), (2, true)] {
+            let go_options = GoOptions {
+                mate,
+                ..Default::default()
+            };
+            let mut search = search_for(
+                board,
+                &go_options,
+                &Options::default(),
+                &mut transposition_table,
+            );
+
+            assert!(!search.pruning);
+
+            let principal_variation = search.iterative_deepening().expect("Writing to stdout");
+
+            assert!(!principal_variation.is_empty());
+            assert_eq!(search.mate_found, found);
+            assert_eq!(search.no_mate_found(), !found);
+        }
+    }
+
+    #[test]
+    fn a_huge_mate_is_searched_as_deep_as_possible() {
+        let board = Board::default();
+        let go_options = GoOptions {
+            mate: usize::MAX,
+            ..Default::default()
+        };
+        let mut transposition_table = TranspositionTable::new(1);
+        let search = search_for(
+            board,
+            &go_options,
+            &Options::default(),
+            &mut transposition_table,
+        );
+
+        assert_eq!(search.max_depth, MAX_DEPTH);
+    }
+
+    #[test]
+    fn mate_scores_are_stored_as_the_distance_from_the_node() {
+        // Mated in seven plies from the root is mated in three from a node at ply four
+        assert_eq!(score_to_table(MATE - 7, 4), MATE - 3);
+        assert_eq!(score_to_table(-MATE + 7, 4), -MATE + 3);
+        // And in five from the same position reached at ply two
+        assert_eq!(score_from_table(MATE - 3, 2), MATE - 5);
+        assert_eq!(score_from_table(-MATE + 3, 2), -MATE + 5);
+
+        assert_eq!(score_to_table(250, 4), 250);
+        assert_eq!(score_from_table(-250, 4), -250);
+    }
+
+    #[test]
+    fn mates_in_one_instead_of_stalemating() {
+        // Qb6 takes every square from the king, but leaves it without a check
+        let chess_move = best_move("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1", 4);
+
+        assert_eq!(chess_move, self::chess_move("b1b7"));
+    }
+
+    #[test]
+    fn promotes_instead_of_capturing_a_knight() {
+        let chess_move = best_move("7k/1P6/8/8/8/8/8/6nK w - - 0 1", 1);
+
+        assert_eq!(chess_move, self::chess_move("b7b8q"));
+    }
+
+    #[test]
+    fn sees_a_mate_of_checks_beyond_the_depth() {
+        // Nf7+ Kg8 Nh6+ Kh8 Qg8+ Rxg8 Nf7# takes seven plies, but every move of ours is a check
+        let pv = principal_variation("r6k/6pp/8/6N1/2Q5/8/5PPP/6K1 w - - 0 1", 4);
+        let mating_line = ["g5f7", "h8g8", "f7h6", "g8h8", "c4g8"].map(chess_move);
+
+        assert!(pv.starts_with(&mating_line));
+    }
+
+    #[test]
+    fn finds_a_fork_with_pruning_on() {
+        // Nd6+ forks the king and the queen, a quiet looking check in a node that's far behind
+        let chess_move = best_move("2q1k3/8/8/1N6/8/8/8/4K3 w - - 0 1", 4);
+
+        assert_eq!(chess_move, self::chess_move("b5d6"));
+    }
+
+    #[test]
+    fn has_check_finds_the_squares_that_give_check() {
+        let has_check_in = |fen| has_check(&Board::from_str(fen).expect("Valid FEN"));
+
+        // The rook goes up to a8, the pawn to d7
+        assert!(has_check_in("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+        assert!(has_check_in("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1"));
+        // The knight is too far away and the rook's way up is blocked by a pawn of its own
+        assert!(!has_check_in("4k3/8/8/8/8/8/8/4K2N w - - 0 1"));
+        assert!(!has_check_in("4k3/8/8/8/8/8/P7/R3K3 w - - 0 1"));
+    }
+}
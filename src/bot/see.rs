@@ -0,0 +1,148 @@
+use {
+    super::piece_to_score,
+    chess::{
+        BitBoard, Board, ChessMove, Color, EMPTY, Piece, Square, get_bishop_moves, get_king_moves,
+        get_knight_moves, get_pawn_attacks, get_rook_moves,
+    },
+};
+
+// Least valuable first, the order attackers join an exchange in
+const EXCHANGE_ORDER: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+// Static exchange evaluation, the material the side to move ends up with when both sides keep
+// capturing on the destination square with their least valuable piece, and stop when it stops paying.
+// Sliders behind the pieces that already took part join in, so batteries and x-rays are counted
+pub fn see(board: &Board, chess_move: ChessMove) -> isize {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+
+    let Some(mut attacker) = board.piece_on(source) else {
+        return 0;
+    };
+
+    let mut occupied = *board.combined() ^ BitBoard::from_square(source);
+
+    // A pawn moving to another file onto an empty square took en passant
+    let captured = match board.piece_on(dest) {
+        Some(piece) => piece_to_score(piece),
+        None if attacker == Piece::Pawn && source.get_file() != dest.get_file() => {
+            let captured_square = Square::make_square(source.get_rank(), dest.get_file());
+            occupied ^= BitBoard::from_square(captured_square);
+
+            piece_to_score(Piece::Pawn)
+        }
+        None => 0,
+    };
+
+    // There are never more than 32 captures in an exchange, one for each piece
+    let mut gains = [0; 32];
+    let mut length = 1;
+
+    gains[0] = captured;
+
+    if let Some(promotion) = chess_move.get_promotion() {
+        gains[0] += piece_to_score(promotion) - piece_to_score(Piece::Pawn);
+        attacker = promotion;
+    }
+
+    let mut side = !board.side_to_move();
+
+    loop {
+        let attackers = attackers_to(board, dest, occupied) & board.color_combined(side);
+
+        let Some((piece, square)) = least_valuable(board, attackers) else {
+            break;
+        };
+
+        // The king can only take when nothing is left to take it back
+        if piece == Piece::King
+            && attackers_to(board, dest, occupied) & board.color_combined(!side) != EMPTY
+        {
+            break;
+        }
+
+        // What this side wins by taking, if the other side doesn't take back
+        gains[length] = piece_to_score(attacker) - gains[length - 1];
+        length += 1;
+
+        occupied ^= BitBoard::from_square(square);
+        attacker = piece;
+        side = !side;
+    }
+
+    // Either side can stop capturing whenever going on would lose more than it wins
+    for index in (1..length).rev() {
+        gains[index - 1] = -(-gains[index - 1]).max(gains[index]);
+    }
+
+    gains[0]
+}
+
+// Every piece of both colors attacking the square, with only the occupied squares blocking the sliders
+fn attackers_to(board: &Board, square: Square, occupied: BitBoard) -> BitBoard {
+    let diagonal = board.pieces(Piece::Bishop) | board.pieces(Piece::Queen);
+    let straight = board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
+    let white_pawns = board.pieces(Piece::Pawn) & board.color_combined(Color::White);
+    let black_pawns = board.pieces(Piece::Pawn) & board.color_combined(Color::Black);
+
+    // A pawn attacks the square if a pawn of the other color on the square would attack the pawn
+    let attackers = get_pawn_attacks(square, Color::Black, white_pawns)
+        | get_pawn_attacks(square, Color::White, black_pawns)
+        | get_knight_moves(square) & board.pieces(Piece::Knight)
+        | get_king_moves(square) & board.pieces(Piece::King)
+        | get_bishop_moves(square, occupied) & diagonal
+        | get_rook_moves(square, occupied) & straight;
+
+    // Pieces that were already traded off the board don't attack anything
+    attackers & occupied
+}
+
+fn least_valuable(board: &Board, attackers: BitBoard) -> Option<(Piece, Square)> {
+    EXCHANGE_ORDER.iter().find_map(|&piece| {
+        let pieces = attackers & board.pieces(piece);
+
+        (pieces != EMPTY).then(|| (piece, pieces.to_square()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    fn see_of(fen: &str, notation: &str) -> isize {
+        let board = Board::from_str(fen).expect("Valid FEN");
+        let chess_move = ChessMove::from_str(notation).expect("Valid move");
+
+        see(&board, chess_move)
+    }
+
+    #[test]
+    fn wins_a_hanging_piece() {
+        assert_eq!(see_of("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1", "d1d5"), 320);
+    }
+
+    #[test]
+    fn loses_the_queen_for_a_defended_pawn() {
+        assert_eq!(see_of("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", "d1d5"), -800);
+    }
+
+    #[test]
+    fn counts_the_rook_behind_the_rook() {
+        // The second rook only joins in through the first, after Rxd5 Rxd5 it takes back for the knight
+        assert_eq!(see_of("3rk3/8/8/3n4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 320);
+        // Rxd5 exd5 Rxd5 gets a knight and a pawn for the rook
+        assert_eq!(see_of("4k3/8/4p3/3n4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), -80);
+    }
+
+    #[test]
+    fn a_quiet_move_can_hang_the_piece() {
+        assert_eq!(see_of("4k3/8/4p3/8/8/8/8/3QK3 w - - 0 1", "d1d5"), -900);
+    }
+}
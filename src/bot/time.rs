@@ -0,0 +1,196 @@
+use {
+    super::{
+        GoOptions, MoveTime,
+        options::{MOVE_OVERHEAD, Options},
+    },
+    chess::Color,
+    std::time::{Duration, Instant},
+};
+
+// How many moves we budget for when the GUI doesn't send movestogo
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// How many times the planned time a single move is allowed to use before it's cut off
+const HARD_LIMIT_FACTOR: u32 = 4;
+
+// Never spend more than this fraction of what's left on the clock on one move
+const MAX_CLOCK_FRACTION: u32 = 3;
+
+// Even the last move before the time control keeps this fraction of the clock back, the clock is only
+// looked at every so many nodes and the search still has to wind down after that
+const LAST_MOVE_RESERVE: u32 = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimeManager {
+    start: Instant,
+    // Past this, no new iteration is started
+    soft_limit: Option<Duration>,
+    // Past this, the search is aborted
+    hard_limit: Option<Duration>,
+}
+
+impl TimeManager {
+    pub fn new(go_options: &GoOptions, options: &Options, side: Color) -> Self {
+        let start = Instant::now();
+
+        // Time kept in reserve for the GUI and the pipes between us, so we don't flag with time on the clock
+        let move_overhead = Duration::from_millis(options.spin(MOVE_OVERHEAD) as u64);
+
+        let (time, increment) = match side {
+            Color::White => (go_options.white_time, go_options.white_increment_time),
+            Color::Black => (go_options.black_time, go_options.black_increment_time),
+        };
+
+        let (soft_limit, hard_limit) = match (go_options.move_time, time) {
+            (MoveTime::Finite(move_time), _) => {
+                let limit = move_time.saturating_sub(move_overhead);
+                (Some(limit), Some(limit))
+            }
+            (MoveTime::Infinite, _) | (MoveTime::NotSpecified, None) => (None, None),
+            (MoveTime::NotSpecified, Some(time)) => {
+                let moves_to_go = match go_options.moves_to_go {
+                    0 => DEFAULT_MOVES_TO_GO,
+                    moves => moves.min(DEFAULT_MOVES_TO_GO as usize) as u32,
+                };
+
+                let (soft, hard) =
+                    clock_limits(time.saturating_sub(move_overhead), increment, moves_to_go);
+                (Some(soft), Some(hard))
+            }
+        };
+
+        Self {
+            start,
+            soft_limit,
+            hard_limit,
+        }
+    }
+
+    // Starts the clock over, used when a ponder search turns into a real one
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn soft_limit_reached(&self) -> bool {
+        self.soft_limit.is_some_and(|limit| self.elapsed() >= limit)
+    }
+
+    pub fn hard_limit_reached(&self) -> bool {
+        self.hard_limit.is_some_and(|limit| self.elapsed() >= limit)
+    }
+}
+
+fn clock_limits(
+    available: Duration,
+    increment: Duration,
+    moves_to_go: u32,
+) -> (Duration, Duration) {
+    // With the last move before the time control there's no reason to save more than the reserve
+    let max_time = if moves_to_go == 1 {
+        available - available / LAST_MOVE_RESERVE
+    } else {
+        available / MAX_CLOCK_FRACTION
+    };
+
+    let planned = available / moves_to_go + increment * 3 / 4;
+
+    let soft = planned.min(max_time);
+    let hard = (planned * HARD_LIMIT_FACTOR).min(max_time);
+
+    (soft, hard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    fn limits(go_options: &GoOptions) -> (Option<Duration>, Option<Duration>) {
+        let time_manager = TimeManager::new(go_options, &Options::default(), Color::White);
+
+        (time_manager.soft_limit, time_manager.hard_limit)
+    }
+
+    #[test]
+    fn the_clock_is_split_over_the_moves_to_go() {
+        // Without movestogo the clock is planned for DEFAULT_MOVES_TO_GO more moves
+        assert_eq!(
+            clock_limits(seconds(30), Duration::ZERO, 30),
+            (seconds(1), seconds(4))
+        );
+        // The hard limit never takes more than a third of the clock
+        assert_eq!(
+            clock_limits(seconds(60), Duration::ZERO, 10),
+            (seconds(6), seconds(20))
+        );
+    }
+
+    #[test]
+    fn the_last_move_keeps_a_reserve() {
+        assert_eq!(
+            clock_limits(seconds(10), Duration::ZERO, 1),
+            (seconds(9), seconds(9))
+        );
+    }
+
+    #[test]
+    fn the_increment_is_spent_but_not_beyond_the_clock() {
+        let (soft, hard) = clock_limits(seconds(60), seconds(4), 30);
+        assert_eq!(soft, seconds(5));
+        assert_eq!(hard, seconds(20));
+
+        // Living off the increment, a third of what's on the clock is still the most a move gets
+        let (soft, hard) = clock_limits(seconds(3), seconds(4), 30);
+        assert_eq!(soft, seconds(1));
+        assert_eq!(hard, seconds(1));
+    }
+
+    #[test]
+    fn a_nearly_empty_clock_leaves_no_time() {
+        assert_eq!(
+            clock_limits(Duration::ZERO, Duration::ZERO, 30),
+            (Duration::ZERO, Duration::ZERO)
+        );
+
+        // Less on the clock than the move overhead
+        let go_options = GoOptions {
+            white_time: Some(Duration::from_millis(10)),
+            ..GoOptions::default()
+        };
+        assert_eq!(
+            limits(&go_options),
+            (Some(Duration::ZERO), Some(Duration::ZERO))
+        );
+    }
+
+    #[test]
+    fn movetime_is_used_up_to_the_move_overhead() {
+        let go_options = GoOptions {
+            white_time: Some(seconds(60)),
+            move_time: MoveTime::Finite(seconds(1)),
+            ..GoOptions::default()
+        };
+        let limit = seconds(1) - Duration::from_millis(30);
+
+        assert_eq!(limits(&go_options), (Some(limit), Some(limit)));
+    }
+
+    #[test]
+    fn a_huge_movestogo_is_the_default() {
+        let go_options = GoOptions {
+            white_time: Some(seconds(30)),
+            moves_to_go: usize::MAX,
+            ..GoOptions::default()
+        };
+        let expected = clock_limits(seconds(30) - Duration::from_millis(30), Duration::ZERO, 30);
+
+        assert_eq!(limits(&go_options), (Some(expected.0), Some(expected.1)));
+    }
+}
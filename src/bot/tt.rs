@@ -0,0 +1,205 @@
+use {chess::ChessMove, std::mem::size_of};
+
+pub const DEFAULT_HASH_MEGABYTES: usize = 16;
+pub const MIN_HASH_MEGABYTES: usize = 1;
+pub const MAX_HASH_MEGABYTES: usize = 4096;
+
+// What the stored score says about the real score of the position
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    // The search failed high, the real score is at least this
+    Lower,
+    // The search failed low, the real score is at most this
+    Upper,
+}
+
+// Scores are always stored from the point of view of the side to move in the position
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    key: u64,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    generation: u8,
+    pub best_move: Option<ChessMove>,
+}
+
+#[derive(Debug)]
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    // Bumped for every search, so entries from old searches get replaced first
+    generation: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MEGABYTES)
+    }
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let megabytes = megabytes.clamp(MIN_HASH_MEGABYTES, MAX_HASH_MEGABYTES);
+        let length = megabytes * 1024 * 1024 / size_of::<Option<Entry>>();
+
+        Self {
+            entries: vec![None; length],
+            generation: 0,
+        }
+    }
+
+    pub fn resize(&mut self, megabytes: usize) {
+        *self = Self::new(megabytes);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.index(key)].filter(|entry| entry.key == key)
+    }
+
+    // Depth preferred replacement, but anything left over from an earlier search can always go
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: usize,
+        bound: Bound,
+        score: isize,
+        best_move: Option<ChessMove>,
+    ) {
+        let index = self.index(key);
+        let slot = &mut self.entries[index];
+
+        let mut best_move = best_move;
+
+        if let Some(old) = slot {
+            let replace = old.generation != self.generation
+                || bound == Bound::Exact
+                || depth + 2 >= old.depth as usize;
+
+            if !replace {
+                return;
+            }
+
+            // A fail low doesn't know a best move, so keep the one we already had for this position
+            if old.key == key && best_move.is_none() {
+                best_move = old.best_move;
+            }
+        }
+
+        *slot = Some(Entry {
+            key,
+            score: score as i32,
+            depth: depth as u8,
+            bound,
+            generation: self.generation,
+            best_move,
+        });
+    }
+
+    // Permille of the table used by the current search, sampled from the first thousand slots
+    pub fn hashfull(&self) -> usize {
+        self.entries
+            .iter()
+            .take(1000)
+            .flatten()
+            .filter(|entry| entry.generation == self.generation)
+            .count()
+            * 1000
+            / self.entries.len().min(1000)
+    }
+
+    fn index(&self, key: u64) -> usize {
+        // Maps the key onto the table without the bias of a modulo
+        ((key as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    // Small keys all land in the first slot, so they push each other out
+    const KEY: u64 = 1;
+    const OTHER_KEY: u64 = 2;
+
+    fn chess_move(notation: &str) -> ChessMove {
+        ChessMove::from_str(notation).expect("Valid move")
+    }
+
+    #[test]
+    fn a_stored_entry_comes_back_for_its_key_only() {
+        let mut transposition_table = TranspositionTable::new(1);
+        transposition_table.store(KEY, 5, Bound::Exact, -123, Some(chess_move("e2e4")));
+
+        let entry = transposition_table.probe(KEY).expect("Stored entry");
+
+        assert_eq!(entry.score, -123);
+        assert_eq!(entry.depth, 5);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.best_move, Some(chess_move("e2e4")));
+        assert!(transposition_table.probe(OTHER_KEY).is_none());
+    }
+
+    #[test]
+    fn deep_entries_of_the_current_search_are_kept() {
+        let mut transposition_table = TranspositionTable::new(1);
+        transposition_table.store(KEY, 10, Bound::Lower, 0, None);
+
+        // Too shallow to replace it
+        transposition_table.store(OTHER_KEY, 7, Bound::Lower, 0, None);
+        assert!(transposition_table.probe(KEY).is_some());
+
+        // Close enough in depth
+        transposition_table.store(OTHER_KEY, 8, Bound::Upper, 0, None);
+        assert!(transposition_table.probe(OTHER_KEY).is_some());
+
+        // Exact scores always go in
+        transposition_table.store(KEY, 1, Bound::Exact, 0, None);
+        assert!(transposition_table.probe(KEY).is_some());
+    }
+
+    #[test]
+    fn entries_of_an_earlier_search_are_replaced() {
+        let mut transposition_table = TranspositionTable::new(1);
+        transposition_table.store(KEY, 10, Bound::Lower, 0, None);
+
+        transposition_table.new_search();
+        transposition_table.store(OTHER_KEY, 1, Bound::Lower, 0, None);
+
+        assert!(transposition_table.probe(KEY).is_none());
+        assert!(transposition_table.probe(OTHER_KEY).is_some());
+    }
+
+    #[test]
+    fn a_fail_low_keeps_the_best_move() {
+        let mut transposition_table = TranspositionTable::new(1);
+        transposition_table.store(KEY, 5, Bound::Lower, 100, Some(chess_move("e2e4")));
+        transposition_table.store(KEY, 6, Bound::Upper, 50, None);
+
+        let entry = transposition_table.probe(KEY).expect("Stored entry");
+
+        assert_eq!(entry.bound, Bound::Upper);
+        assert_eq!(entry.score, 50);
+        assert_eq!(entry.best_move, Some(chess_move("e2e4")));
+
+        // The best move of another position is no use though
+        transposition_table.store(OTHER_KEY, 6, Bound::Upper, 50, None);
+
+        assert_eq!(
+            transposition_table
+                .probe(OTHER_KEY)
+                .expect("Stored entry")
+                .best_move,
+            None
+        );
+    }
+}